@@ -14,6 +14,7 @@ Optimized for:
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -188,8 +189,18 @@ impl BoundedSearch {
     }
 
     /// Check if we should stop searching.
-    /// Stops after max_results only if we have at least min_results.
-    pub(crate) fn should_stop(&self, current_count: usize, files_matched: usize) -> bool {
+    /// Stops after max_results only if we have at least min_results, or
+    /// immediately if `controller` has been cancelled.
+    pub(crate) fn should_stop(
+        &self,
+        current_count: usize,
+        files_matched: usize,
+        controller: Option<&SearchController>,
+    ) -> bool {
+        if controller.is_some_and(SearchController::is_cancelled) {
+            return true;
+        }
+
         self.stop_early
             && current_count >= self.max_results
             && files_matched >= self.min_results
@@ -202,6 +213,56 @@ impl Default for BoundedSearch {
     }
 }
 
+/// Cooperative cancellation handle for an in-flight search, so an
+/// interactive UI can abandon a search the instant the user types the next
+/// keystroke instead of waiting for it to run to completion.
+///
+/// Cloning a `SearchController` shares the same underlying flag and
+/// counter (both fields are `Arc`-backed), so the caller can hold one
+/// clone and cancel it while a worker thread polls another clone via
+/// `BoundedSearch::should_stop`.
+#[derive(Clone, Debug)]
+pub(crate) struct SearchController {
+    stop: Arc<AtomicBool>,
+    processed: Arc<AtomicUsize>,
+}
+
+impl SearchController {
+    /// Create a new, not-yet-cancelled controller with a zeroed counter.
+    pub(crate) fn new() -> Self {
+        SearchController {
+            stop: Arc::new(AtomicBool::new(false)),
+            processed: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Signal the search to stop as soon as it next checks.
+    pub(crate) fn cancel(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether `cancel()` has been called.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Record that `amount` more files/bytes have been processed.
+    pub(crate) fn record_progress(&self, amount: usize) {
+        self.processed.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    /// Cheap read of files/bytes processed so far, for throughput display.
+    pub(crate) fn processed(&self) -> usize {
+        self.processed.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for SearchController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -278,9 +339,9 @@ mod tests {
         assert_eq!(bounded.max_results, 50);
         assert_eq!(bounded.min_results, 10);
         assert!(bounded.stop_early);
-        assert!(!bounded.should_stop(49, 10)); // not enough results
-        assert!(bounded.should_stop(50, 10)); // enough results
-        assert!(!bounded.should_stop(50, 5)); // not enough files matched
+        assert!(!bounded.should_stop(49, 10, None)); // not enough results
+        assert!(bounded.should_stop(50, 10, None)); // enough results
+        assert!(!bounded.should_stop(50, 5, None)); // not enough files matched
     }
 
     #[test]
@@ -288,6 +349,47 @@ mod tests {
         let bounded = BoundedSearch::unlimited();
         assert_eq!(bounded.max_results, usize::MAX);
         assert!(!bounded.stop_early);
-        assert!(!bounded.should_stop(1000, 0));
+        assert!(!bounded.should_stop(1000, 0, None));
+    }
+
+    #[test]
+    fn test_search_controller_cancel() {
+        let controller = SearchController::new();
+        assert!(!controller.is_cancelled());
+
+        controller.cancel();
+        assert!(controller.is_cancelled());
+    }
+
+    #[test]
+    fn test_search_controller_shared_across_clones() {
+        let controller = SearchController::new();
+        let worker_handle = controller.clone();
+
+        assert!(!worker_handle.is_cancelled());
+        controller.cancel();
+        assert!(worker_handle.is_cancelled());
+    }
+
+    #[test]
+    fn test_search_controller_progress() {
+        let controller = SearchController::new();
+        assert_eq!(controller.processed(), 0);
+
+        controller.record_progress(10);
+        controller.record_progress(5);
+        assert_eq!(controller.processed(), 15);
+    }
+
+    #[test]
+    fn test_bounded_search_stops_on_cancellation() {
+        let bounded = BoundedSearch::ide_defaults();
+        let controller = SearchController::new();
+
+        // Far below max_results/min_results, so only cancellation triggers a stop.
+        assert!(!bounded.should_stop(0, 0, Some(&controller)));
+
+        controller.cancel();
+        assert!(bounded.should_stop(0, 0, Some(&controller)));
     }
 }
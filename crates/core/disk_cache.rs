@@ -0,0 +1,449 @@
+/*!
+Persistent on-disk second tier for `FileContentCache`.
+
+The in-memory cache in `optimizer::FileContentCache` only survives a
+single process: the next run has to re-read and re-score every file from
+scratch even when nothing changed, the way czkawka's duplicate-file cache
+makes a rescan of an unchanged tree nearly instant by trusting a stored
+fingerprint instead of re-hashing content.
+
+Entries are keyed by a `FileFingerprint` of `(path, mtime, size)`; a
+lookup only returns a hit when the fingerprint still matches the file's
+current `std::fs::metadata`, so edited files are transparently treated as
+misses. Two kinds of payloads share one on-disk log:
+- raw file content, keyed by path alone (`pattern_hash` of `0`)
+- per-file match/score results, keyed by `(path, hash of the query
+  pattern)`, since regex objects and `HeuristicConfig` aren't worth
+  serializing -- only their output is
+
+The log is append-only: `put_content`/`put_scores` always write a new
+record rather than rewriting the file in place, so a crash mid-write
+can't corrupt earlier entries. The index is rebuilt by replaying the log
+once at `open` time, with later records for the same key shadowing
+earlier ones. `gc` is the only operation that rewrites the file, and it
+only runs when the caller asks for it.
+*/
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+
+/// Reserved pattern hash marking a record as raw file content rather than
+/// a pattern-scoped match/score payload.
+const CONTENT_PATTERN_HASH: u64 = 0;
+
+/// A fingerprint of a file's identity at the time it was cached, cheap to
+/// recompute from `std::fs::metadata` and good enough to detect almost
+/// every edit without reading the file's content.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct FileFingerprint {
+    mtime_secs: u64,
+    mtime_nanos: u32,
+    size: u64,
+}
+
+impl FileFingerprint {
+    /// Builds a fingerprint from `path`'s current filesystem metadata.
+    pub(crate) fn for_path(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let since_epoch = modified
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(FileFingerprint {
+            mtime_secs: since_epoch.as_secs(),
+            mtime_nanos: since_epoch.subsec_nanos(),
+            size: metadata.len(),
+        })
+    }
+}
+
+/// One cached payload: the fingerprint it was captured under, plus either
+/// raw content or a serialized score payload.
+#[derive(Clone, Debug)]
+struct CacheEntry {
+    fingerprint: FileFingerprint,
+    payload: Vec<u8>,
+}
+
+/// Hashes `pattern` into the key space used to scope per-query score
+/// payloads, so two different queries against the same file never
+/// collide. Never returns `CONTENT_PATTERN_HASH`, so a pathological
+/// pattern can't be mistaken for a raw-content entry.
+fn hash_pattern(pattern: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    pattern.hash(&mut hasher);
+    match hasher.finish() {
+        CONTENT_PATTERN_HASH => CONTENT_PATTERN_HASH.wrapping_add(1),
+        hash => hash,
+    }
+}
+
+/// Persistent second tier backing `FileContentCache`: a lazily-loaded
+/// index over a single append-only log file in `cache_dir`.
+#[derive(Clone, Debug)]
+pub(crate) struct DiskCache {
+    log_path: PathBuf,
+    index: HashMap<(PathBuf, u64), CacheEntry>,
+}
+
+impl DiskCache {
+    /// Opens (creating if necessary) the cache log under `cache_dir` and
+    /// replays it into an in-memory index. A missing or unreadable log is
+    /// treated as an empty cache rather than an error, since losing the
+    /// disk tier should never stop a search from running.
+    pub(crate) fn open(cache_dir: &Path) -> Result<Self> {
+        fs::create_dir_all(cache_dir)
+            .with_context(|| format!("creating cache directory {}", cache_dir.display()))?;
+        let log_path = cache_dir.join("fluid-grep-cache.log");
+
+        let index = File::open(&log_path)
+            .ok()
+            .and_then(|file| Self::replay(file).ok())
+            .unwrap_or_default();
+
+        Ok(DiskCache { log_path, index })
+    }
+
+    /// Replays every record in `file` in order, keeping only the last
+    /// record written for each `(path, pattern_hash)` key. Stops at the
+    /// first truncated/corrupt record rather than erroring, since an
+    /// append log can legitimately end mid-write after a crash.
+    fn replay(file: File) -> io::Result<HashMap<(PathBuf, u64), CacheEntry>> {
+        let mut reader = BufReader::new(file);
+        let mut index = HashMap::new();
+
+        while let Some((path, pattern_hash, entry)) = Self::read_record(&mut reader)? {
+            index.insert((path, pattern_hash), entry);
+        }
+
+        Ok(index)
+    }
+
+    /// Reads one record, or `None` at a clean end-of-file. A short read
+    /// (`UnexpectedEof`) partway through a record is treated as "nothing
+    /// more to read", matching `replay`'s tolerance for a truncated tail --
+    /// a crash can leave a partial record anywhere in the record's byte
+    /// layout, not just at its first field. Any other I/O error is
+    /// propagated.
+    fn read_record(
+        reader: &mut impl Read,
+    ) -> io::Result<Option<(PathBuf, u64, CacheEntry)>> {
+        match Self::read_record_inner(reader) {
+            Ok(record) => Ok(Some(record)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reads one record's fields in full, propagating any read error
+    /// (including a short read partway through) so `read_record` can
+    /// uniformly treat every truncation point as end-of-log.
+    fn read_record_inner(reader: &mut impl Read) -> io::Result<(PathBuf, u64, CacheEntry)> {
+        let pattern_hash = read_u64(reader)?;
+        let mtime_secs = read_u64(reader)?;
+        let mtime_nanos = read_u32(reader)?;
+        let size = read_u64(reader)?;
+        let path_len = read_u32(reader)? as usize;
+        let path_bytes = read_exact_vec(reader, path_len)?;
+        let payload_len = read_u32(reader)? as usize;
+        let payload = read_exact_vec(reader, payload_len)?;
+
+        let path = PathBuf::from(String::from_utf8_lossy(&path_bytes).into_owned());
+        let entry = CacheEntry {
+            fingerprint: FileFingerprint {
+                mtime_secs,
+                mtime_nanos,
+                size,
+            },
+            payload,
+        };
+        Ok((path, pattern_hash, entry))
+    }
+
+    /// Appends one record to the log and updates the in-memory index.
+    fn append(&mut self, path: &Path, pattern_hash: u64, entry: CacheEntry) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_path)
+            .with_context(|| format!("opening cache log {}", self.log_path.display()))?;
+
+        let path_bytes = path.to_string_lossy().into_owned().into_bytes();
+        file.write_all(&pattern_hash.to_le_bytes())?;
+        file.write_all(&entry.fingerprint.mtime_secs.to_le_bytes())?;
+        file.write_all(&entry.fingerprint.mtime_nanos.to_le_bytes())?;
+        file.write_all(&entry.fingerprint.size.to_le_bytes())?;
+        file.write_all(&(path_bytes.len() as u32).to_le_bytes())?;
+        file.write_all(&path_bytes)?;
+        file.write_all(&(entry.payload.len() as u32).to_le_bytes())?;
+        file.write_all(&entry.payload)?;
+
+        self.index.insert((path.to_path_buf(), pattern_hash), entry);
+        Ok(())
+    }
+
+    /// Returns `path`'s cached raw content if present and still fresh
+    /// against its current on-disk fingerprint.
+    pub(crate) fn get_content(&self, path: &Path) -> Option<&[u8]> {
+        self.get(path, CONTENT_PATTERN_HASH)
+    }
+
+    /// Caches `content` as `path`'s raw bytes under its current
+    /// fingerprint.
+    pub(crate) fn put_content(&mut self, path: &Path, content: &[u8]) -> Result<()> {
+        self.put(path, CONTENT_PATTERN_HASH, content)
+    }
+
+    /// Returns the cached score/match payload for `(path, pattern)` if
+    /// present and still fresh against `path`'s current fingerprint.
+    pub(crate) fn get_scores(&self, path: &Path, pattern: &str) -> Option<&[u8]> {
+        self.get(path, hash_pattern(pattern))
+    }
+
+    /// Caches `payload` as the score/match results for `(path, pattern)`
+    /// under `path`'s current fingerprint.
+    pub(crate) fn put_scores(&mut self, path: &Path, pattern: &str, payload: &[u8]) -> Result<()> {
+        self.put(path, hash_pattern(pattern), payload)
+    }
+
+    fn get(&self, path: &Path, pattern_hash: u64) -> Option<&[u8]> {
+        let entry = self.index.get(&(path.to_path_buf(), pattern_hash))?;
+        let current = FileFingerprint::for_path(path).ok()?;
+        (entry.fingerprint == current).then_some(entry.payload.as_slice())
+    }
+
+    fn put(&mut self, path: &Path, pattern_hash: u64, payload: &[u8]) -> Result<()> {
+        let fingerprint = FileFingerprint::for_path(path)
+            .with_context(|| format!("stat'ing {} for cache fingerprint", path.display()))?;
+        self.append(
+            path,
+            pattern_hash,
+            CacheEntry {
+                fingerprint,
+                payload: payload.to_vec(),
+            },
+        )
+    }
+
+    /// Number of entries currently indexed (content and score payloads
+    /// combined), regardless of whether their path still exists.
+    pub(crate) fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Drops every entry whose path no longer exists on disk and, if any
+    /// were dropped, rewrites the log file to contain only what survives.
+    /// Returns the number of entries removed.
+    pub(crate) fn gc(&mut self) -> Result<usize> {
+        let before = self.index.len();
+        self.index.retain(|(path, _), _| path.exists());
+        let removed = before - self.index.len();
+
+        if removed > 0 {
+            self.rewrite_log()?;
+        }
+        Ok(removed)
+    }
+
+    /// Rewrites the log file from scratch to hold exactly `self.index`,
+    /// compacting away superseded and garbage-collected records.
+    fn rewrite_log(&self) -> Result<()> {
+        let tmp_path = self.log_path.with_extension("log.tmp");
+        {
+            let mut file = File::create(&tmp_path)
+                .with_context(|| format!("creating {}", tmp_path.display()))?;
+            for ((path, pattern_hash), entry) in &self.index {
+                let path_bytes = path.to_string_lossy().into_owned().into_bytes();
+                file.write_all(&pattern_hash.to_le_bytes())?;
+                file.write_all(&entry.fingerprint.mtime_secs.to_le_bytes())?;
+                file.write_all(&entry.fingerprint.mtime_nanos.to_le_bytes())?;
+                file.write_all(&entry.fingerprint.size.to_le_bytes())?;
+                file.write_all(&(path_bytes.len() as u32).to_le_bytes())?;
+                file.write_all(&path_bytes)?;
+                file.write_all(&(entry.payload.len() as u32).to_le_bytes())?;
+                file.write_all(&entry.payload)?;
+            }
+        }
+        fs::rename(&tmp_path, &self.log_path)
+            .with_context(|| format!("replacing {}", self.log_path.display()))?;
+        Ok(())
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_exact_vec(reader: &mut impl Read, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache_dir(name: &str) -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!(
+            "fluid-grep-disk-cache-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_put_and_get_content_round_trips() {
+        let dir = temp_cache_dir("content");
+        let target = dir.join("target.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&target, b"hello world").unwrap();
+
+        let mut cache = DiskCache::open(&dir).unwrap();
+        cache.put_content(&target, b"hello world").unwrap();
+
+        assert_eq!(cache.get_content(&target), Some(b"hello world".as_slice()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stale_fingerprint_is_a_miss() {
+        let dir = temp_cache_dir("stale");
+        let target = dir.join("target.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&target, b"version one").unwrap();
+
+        let mut cache = DiskCache::open(&dir).unwrap();
+        cache.put_content(&target, b"version one").unwrap();
+
+        // Changing the file's size changes its fingerprint even if the
+        // mtime resolution is too coarse to have ticked over.
+        fs::write(&target, b"version two (longer)").unwrap();
+        assert_eq!(cache.get_content(&target), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_scores_are_scoped_per_pattern() {
+        let dir = temp_cache_dir("scores");
+        let target = dir.join("target.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&target, b"content").unwrap();
+
+        let mut cache = DiskCache::open(&dir).unwrap();
+        cache.put_scores(&target, "fn main", b"scores-for-fn-main").unwrap();
+        cache.put_scores(&target, "struct Foo", b"scores-for-struct-foo").unwrap();
+
+        assert_eq!(
+            cache.get_scores(&target, "fn main"),
+            Some(b"scores-for-fn-main".as_slice())
+        );
+        assert_eq!(
+            cache.get_scores(&target, "struct Foo"),
+            Some(b"scores-for-struct-foo".as_slice())
+        );
+        assert_eq!(cache.get_scores(&target, "no such query"), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reopen_replays_log_into_index() {
+        let dir = temp_cache_dir("reopen");
+        let target = dir.join("target.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&target, b"persisted").unwrap();
+
+        {
+            let mut cache = DiskCache::open(&dir).unwrap();
+            cache.put_content(&target, b"persisted").unwrap();
+        }
+
+        let reopened = DiskCache::open(&dir).unwrap();
+        assert_eq!(reopened.get_content(&target), Some(b"persisted".as_slice()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_later_write_shadows_earlier_one_for_same_key() {
+        let dir = temp_cache_dir("shadow");
+        let target = dir.join("target.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&target, b"v1").unwrap();
+
+        let mut cache = DiskCache::open(&dir).unwrap();
+        cache.put_content(&target, b"v1").unwrap();
+        cache.put_content(&target, b"v1-again").unwrap();
+
+        assert_eq!(cache.get_content(&target), Some(b"v1-again".as_slice()));
+        assert_eq!(cache.len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_truncated_trailing_record_does_not_drop_earlier_valid_entries() {
+        let dir = temp_cache_dir("truncated-tail");
+        let target = dir.join("target.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&target, b"persisted").unwrap();
+
+        {
+            let mut cache = DiskCache::open(&dir).unwrap();
+            cache.put_content(&target, b"persisted").unwrap();
+        }
+
+        // Simulate a crash mid-write: append a second record that only got
+        // its first two fields (pattern_hash, mtime_secs) flushed before
+        // the process died, with nothing after.
+        let log_path = dir.join("fluid-grep-cache.log");
+        let mut file = OpenOptions::new().append(true).open(&log_path).unwrap();
+        file.write_all(&CONTENT_PATTERN_HASH.to_le_bytes()).unwrap();
+        file.write_all(&123u64.to_le_bytes()).unwrap();
+        drop(file);
+
+        let reopened = DiskCache::open(&dir).unwrap();
+        assert_eq!(reopened.get_content(&target), Some(b"persisted".as_slice()));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_gc_removes_entries_for_deleted_paths() {
+        let dir = temp_cache_dir("gc");
+        let survivor = dir.join("survivor.txt");
+        let deleted = dir.join("deleted.txt");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&survivor, b"keep me").unwrap();
+        fs::write(&deleted, b"remove me").unwrap();
+
+        let mut cache = DiskCache::open(&dir).unwrap();
+        cache.put_content(&survivor, b"keep me").unwrap();
+        cache.put_content(&deleted, b"remove me").unwrap();
+        fs::remove_file(&deleted).unwrap();
+
+        let removed = cache.gc().unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get_content(&survivor), Some(b"keep me".as_slice()));
+
+        let reopened = DiskCache::open(&dir).unwrap();
+        assert_eq!(reopened.len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,413 @@
+/*!
+FastCDC content-defined chunking for the file content cache.
+
+`FileContentCache` stores whole files as one `Arc<[u8]>` each, which
+wastes memory when two files share long identical regions, or when a
+file is re-inserted after only a few bytes changed -- the common case
+across repeated fuzzy scans of a slowly-edited tree. This module splits
+file content into variable-size chunks at content-defined boundaries
+(Xia et al.'s FastCDC) and stores chunks in a single deduplicated pool
+keyed by chunk hash, so identical regions anywhere in the pool -- across
+files, and across versions of the same file -- are only ever stored once.
+
+Boundaries are found by rolling a 64-bit "gear" fingerprint over the
+content (`fp = (fp << 1) + gear[byte]`) and cutting whenever `fp & mask
+== 0`. Normalized chunking applies a stricter mask (more bits, harder to
+satisfy) before the target average size and a looser mask (fewer bits,
+easier to satisfy) after it, so cuts cluster near the target instead of
+spreading uniformly across the whole `[min_size, max_size]` range.
+*/
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Builds the 256-entry table of pseudo-random 64-bit "gear" values
+/// FastCDC rolls its fingerprint over. Generated deterministically via
+/// splitmix64 rather than drawn from an RNG dependency, so identical
+/// content always chunks identically across runs and machines.
+fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9E3779B97F4A7C15;
+    for slot in table.iter_mut() {
+        state = state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        *slot = z;
+    }
+    table
+}
+
+/// Boundary-tuning knobs for `Chunker`, clamping chunk size into
+/// `[min_size, max_size]` around a target `avg_size`.
+#[derive(Clone, Debug)]
+pub(crate) struct ChunkerConfig {
+    pub min_size: usize,
+    pub avg_size: usize,
+    pub max_size: usize,
+    gear: [u64; 256],
+    /// Stricter mask (more one-bits), applied while the candidate cut
+    /// offset is still short of `avg_size`.
+    mask_small: u64,
+    /// Looser mask (fewer one-bits), applied once the candidate cut
+    /// offset has passed `avg_size`.
+    mask_large: u64,
+}
+
+impl ChunkerConfig {
+    /// Builds a config targeting `avg_size`, clamped to `[min_size,
+    /// max_size]`. `avg_size` should be a power of two for the mask math
+    /// to land on a clean bit count; callers outside this module's tests
+    /// and defaults should pick one (e.g. 8KB, not 8000 bytes).
+    pub(crate) fn new(min_size: usize, avg_size: usize, max_size: usize) -> Self {
+        let avg_bits = (avg_size.max(2) as f64).log2().round() as u32;
+        ChunkerConfig {
+            min_size,
+            avg_size,
+            max_size,
+            gear: gear_table(),
+            mask_small: mask_with_bits(avg_bits + 1),
+            mask_large: mask_with_bits(avg_bits.saturating_sub(1)),
+        }
+    }
+
+    /// Splits `data` into content-defined chunk boundaries, returned as
+    /// `(start, end)` byte ranges that concatenate back to `data`
+    /// exactly. Always cuts at EOF, even if the final chunk is shorter
+    /// than `min_size`.
+    pub(crate) fn chunk_boundaries(&self, data: &[u8]) -> Vec<(usize, usize)> {
+        let mut boundaries = Vec::new();
+        let mut start = 0usize;
+        while start < data.len() {
+            let cut = start + self.find_next_cut(&data[start..]);
+            boundaries.push((start, cut));
+            start = cut;
+        }
+        boundaries
+    }
+
+    /// Finds the next cut point within `data`, relative to `data`'s own
+    /// start (i.e. the length of the next chunk).
+    fn find_next_cut(&self, data: &[u8]) -> usize {
+        let len = data.len();
+        if len <= self.min_size {
+            return len;
+        }
+
+        let scan_limit = len.min(self.max_size);
+        let mut fingerprint: u64 = 0;
+        let mut offset = self.min_size;
+        while offset < scan_limit {
+            fingerprint = (fingerprint << 1).wrapping_add(self.gear[data[offset] as usize]);
+            let mask = if offset < self.avg_size {
+                self.mask_small
+            } else {
+                self.mask_large
+            };
+            if fingerprint & mask == 0 {
+                return offset + 1;
+            }
+            offset += 1;
+        }
+        scan_limit
+    }
+}
+
+impl Default for ChunkerConfig {
+    /// 2KB/8KB/64KB min/avg/max, FastCDC's own commonly-cited defaults.
+    fn default() -> Self {
+        ChunkerConfig::new(2 * 1024, 8 * 1024, 64 * 1024)
+    }
+}
+
+fn mask_with_bits(bits: u32) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        (1u64 << bits.min(63)) - 1
+    }
+}
+
+/// FNV-1a hash of a chunk's bytes, used as its key in `ChunkPool`.
+/// Collisions are a theoretical, not practical, concern at file-cache
+/// scale, and this avoids pulling in a hashing crate for content that's
+/// only ever compared to itself.
+fn hash_chunk(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+    let mut hash = FNV_OFFSET;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Deduplicated pool of content-defined chunks, shared across every file
+/// inserted through it. Re-inserting a file that shares chunks with
+/// something already in the pool only allocates the chunks that are
+/// actually new. Each chunk carries a reference count of how many
+/// still-live files point at it, so `remove` can free a chunk's memory
+/// once nothing references it anymore, instead of keeping every chunk
+/// ever seen for the pool's entire lifetime.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ChunkPool {
+    chunks: HashMap<u64, (Arc<[u8]>, usize)>,
+}
+
+impl ChunkPool {
+    pub(crate) fn new() -> Self {
+        ChunkPool {
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Number of distinct chunks currently stored.
+    pub(crate) fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Splits `content` per `config`, interning each chunk into the pool
+    /// (a chunk whose hash is already present costs nothing further beyond
+    /// bumping its refcount), and returns the ordered hash list needed to
+    /// reassemble `content`. The caller is responsible for passing that
+    /// hash list to `remove` once it's no longer needed, or the chunks
+    /// will never be freed.
+    pub(crate) fn insert(&mut self, content: &[u8], config: &ChunkerConfig) -> Vec<u64> {
+        config
+            .chunk_boundaries(content)
+            .into_iter()
+            .map(|(start, end)| {
+                let slice = &content[start..end];
+                let hash = hash_chunk(slice);
+                match self.chunks.get_mut(&hash) {
+                    Some((existing, refcount)) => {
+                        // FNV-1a isn't collision-resistant; catch a
+                        // collision between two genuinely different chunks
+                        // loudly in debug builds rather than silently
+                        // reassembling the wrong bytes for one of them.
+                        debug_assert_eq!(
+                            existing.as_ref(),
+                            slice,
+                            "hash_chunk collision: two different chunks hashed to {:#x}",
+                            hash
+                        );
+                        *refcount += 1;
+                    }
+                    None => {
+                        self.chunks.insert(hash, (Arc::from(slice), 1));
+                    }
+                }
+                hash
+            })
+            .collect()
+    }
+
+    /// Decrements the refcount of each hash in `hashes`, freeing any chunk
+    /// whose refcount drops to zero. Call with a file's previous hash list
+    /// whenever that file's content is replaced or evicted, so chunks no
+    /// longer referenced by anything are actually released.
+    pub(crate) fn remove(&mut self, hashes: &[u64]) {
+        for &hash in hashes {
+            if let std::collections::hash_map::Entry::Occupied(mut entry) =
+                self.chunks.entry(hash)
+            {
+                let (_, refcount) = entry.get_mut();
+                *refcount -= 1;
+                if *refcount == 0 {
+                    entry.remove();
+                }
+            }
+        }
+    }
+
+    /// Reassembles a file from its chunk hash list, or `None` if any
+    /// hash is missing from the pool (e.g. never inserted, or dropped).
+    pub(crate) fn reassemble(&self, hashes: &[u64]) -> Option<Vec<u8>> {
+        let mut out = Vec::new();
+        for hash in hashes {
+            out.extend_from_slice(&self.chunks.get(hash)?.0);
+        }
+        Some(out)
+    }
+}
+
+/// Chunk-level deduplicated file cache: each file is stored as an
+/// ordered list of chunk hashes into a shared `ChunkPool`, rather than as
+/// one whole-file allocation, so near-duplicate files (and repeated
+/// insertions of a slowly-edited file) only pay for the bytes that
+/// actually differ.
+#[derive(Clone, Debug)]
+pub(crate) struct ChunkedFileCache {
+    pool: ChunkPool,
+    files: HashMap<PathBuf, Vec<u64>>,
+    config: ChunkerConfig,
+}
+
+impl ChunkedFileCache {
+    pub(crate) fn new(config: ChunkerConfig) -> Self {
+        ChunkedFileCache {
+            pool: ChunkPool::new(),
+            files: HashMap::new(),
+            config,
+        }
+    }
+
+    /// Chunks and stores `content` under `path`, reusing any chunk
+    /// already present in the pool from another file or an earlier
+    /// version of this one. If `path` was already tracked, its previous
+    /// chunks are released first so a replaced file doesn't keep its old
+    /// bytes referenced forever.
+    pub(crate) fn insert(&mut self, path: PathBuf, content: &[u8]) {
+        if let Some(old_hashes) = self.files.remove(&path) {
+            self.pool.remove(&old_hashes);
+        }
+        let hashes = self.pool.insert(content, &self.config);
+        self.files.insert(path, hashes);
+    }
+
+    /// Reassembles `path`'s cached content, if present.
+    pub(crate) fn get(&self, path: &Path) -> Option<Vec<u8>> {
+        let hashes = self.files.get(path)?;
+        self.pool.reassemble(hashes)
+    }
+
+    /// Drops `path` from the cache, releasing any chunks it referenced
+    /// that aren't shared by another still-tracked file.
+    pub(crate) fn remove(&mut self, path: &Path) {
+        if let Some(hashes) = self.files.remove(path) {
+            self.pool.remove(&hashes);
+        }
+    }
+
+    /// Number of distinct chunks held in the shared pool.
+    pub(crate) fn chunk_count(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Number of files currently tracked.
+    pub(crate) fn file_count(&self) -> usize {
+        self.files.len()
+    }
+}
+
+impl Default for ChunkedFileCache {
+    fn default() -> Self {
+        ChunkedFileCache::new(ChunkerConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_boundaries_always_cut_at_eof() {
+        let config = ChunkerConfig::new(4, 16, 64);
+        let data = vec![7u8; 10]; // shorter than min_size
+        let boundaries = config.chunk_boundaries(&data);
+        assert_eq!(boundaries, vec![(0, 10)]);
+    }
+
+    #[test]
+    fn test_chunk_boundaries_respect_max_size() {
+        let config = ChunkerConfig::new(4, 16, 32);
+        let data = vec![0u8; 100];
+        let boundaries = config.chunk_boundaries(&data);
+        assert!(boundaries.iter().all(|&(s, e)| e - s <= 32));
+        let total: usize = boundaries.iter().map(|&(s, e)| e - s).sum();
+        assert_eq!(total, data.len());
+    }
+
+    #[test]
+    fn test_reassembly_round_trips_arbitrary_content() {
+        let config = ChunkerConfig::default();
+        let mut pool = ChunkPool::new();
+        let data: Vec<u8> = (0..5000).map(|i| (i % 251) as u8).collect();
+
+        let hashes = pool.insert(&data, &config);
+        let reassembled = pool.reassemble(&hashes).unwrap();
+        assert_eq!(reassembled, data);
+    }
+
+    #[test]
+    fn test_chunk_pool_remove_frees_chunks_unreferenced_by_any_other_file() {
+        let config = ChunkerConfig::new(64, 256, 1024);
+        let mut pool = ChunkPool::new();
+
+        let shared: Vec<u8> = (0..3000).map(|i| (i % 197) as u8).collect();
+        let mut file_a = shared.clone();
+        file_a.extend_from_slice(b"tail of file a");
+        let mut file_b = shared;
+        file_b.extend_from_slice(b"a completely different tail for file b");
+
+        let hashes_a = pool.insert(&file_a, &config);
+        let hashes_b = pool.insert(&file_b, &config);
+        let chunks_with_both = pool.len();
+
+        // Dropping `a`'s reference must not disturb chunks `b` still
+        // shares with it, so `b` keeps reassembling correctly.
+        pool.remove(&hashes_a);
+        assert!(pool.len() < chunks_with_both);
+        assert_eq!(pool.reassemble(&hashes_b).unwrap(), file_b);
+
+        // Once `b` is gone too, nothing references any chunk anymore.
+        pool.remove(&hashes_b);
+        assert_eq!(pool.len(), 0);
+    }
+
+    #[test]
+    fn test_dedup_shares_chunks_across_files() {
+        let config = ChunkerConfig::new(64, 256, 1024);
+        let mut pool = ChunkPool::new();
+
+        let shared: Vec<u8> = (0..3000).map(|i| (i % 197) as u8).collect();
+        let mut file_a = shared.clone();
+        file_a.extend_from_slice(b"tail of file a");
+        let mut file_b = shared.clone();
+        file_b.extend_from_slice(b"a completely different tail for file b");
+
+        let hashes_a = pool.insert(&file_a, &config);
+        let chunk_count_after_a = pool.len();
+        let hashes_b = pool.insert(&file_b, &config);
+        let chunk_count_after_b = pool.len();
+
+        // The two files share a long common prefix, so file b should
+        // reuse most of file a's chunks and only add a few new ones for
+        // its differing tail.
+        assert!(chunk_count_after_b - chunk_count_after_a < hashes_b.len());
+        assert_eq!(pool.reassemble(&hashes_a).unwrap(), file_a);
+        assert_eq!(pool.reassemble(&hashes_b).unwrap(), file_b);
+    }
+
+    #[test]
+    fn test_chunked_file_cache_reinsert_only_grows_pool_by_new_chunks() {
+        let mut cache = ChunkedFileCache::new(ChunkerConfig::new(64, 256, 1024));
+        let path = PathBuf::from("/repo/src/lib.rs");
+
+        let original: Vec<u8> = (0..4000).map(|i| (i % 223) as u8).collect();
+        cache.insert(path.clone(), &original);
+        let chunks_after_first = cache.chunk_count();
+
+        // Re-inserting identical content must not add any new chunks.
+        cache.insert(path.clone(), &original);
+        assert_eq!(cache.chunk_count(), chunks_after_first);
+        assert_eq!(cache.get(&path).unwrap(), original);
+
+        // Appending a small tail should only add a handful of new
+        // chunks, not re-store the whole file.
+        let mut edited = original.clone();
+        edited.extend_from_slice(b"a small edit at the end");
+        cache.insert(path.clone(), &edited);
+        assert!(cache.chunk_count() > chunks_after_first);
+        assert_eq!(cache.get(&path).unwrap(), edited);
+    }
+
+    #[test]
+    fn test_missing_path_is_a_miss() {
+        let cache = ChunkedFileCache::default();
+        assert_eq!(cache.get(Path::new("/never/inserted.rs")), None);
+    }
+}
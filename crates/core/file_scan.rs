@@ -0,0 +1,332 @@
+/*!
+File-access subsystem tuned for the 2-3ms latency target.
+
+Reading whole files into a `String` (UTF-8 validation plus a fresh heap
+allocation per file) is fine for a handful of small files, but it falls
+apart on large ones. This module memory-maps files above a size threshold
+and falls back to a reusable heap buffer for small files, pipes, and
+anything that fails to map. Mapped files are fed to
+`SimdMatcher`/`FuzzyMatcher` without a copy; buffered files are copied
+once out of the reusable buffer into the returned `Arc<[u8]>`.
+*/
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+/// At or above this size, `FileScanner` memory-maps the file instead of
+/// reading it onto the heap.
+const DEFAULT_MMAP_THRESHOLD_BYTES: u64 = 64 * 1024;
+
+/// Number of leading bytes inspected by `looks_binary`.
+const BINARY_CHECK_WINDOW: usize = 8192;
+
+/// Owned view of a file's bytes, however they were obtained. Derefs to
+/// `&[u8]` via `as_bytes` so callers can feed it straight into
+/// `SimdMatcher`/`FuzzyMatcher` without copying.
+pub(crate) enum FileContent {
+    /// Backed by a memory map; zero-copy, valid for as long as this value
+    /// is alive.
+    Mapped(Mmap),
+    /// Backed by a heap buffer, shared via `Arc` so callers can hold it
+    /// alongside cached results without cloning the bytes.
+    Buffered(Arc<[u8]>),
+}
+
+impl FileContent {
+    /// Borrow the file's bytes regardless of how they were obtained.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        match self {
+            FileContent::Mapped(mmap) => &mmap[..],
+            FileContent::Buffered(buf) => buf,
+        }
+    }
+}
+
+/// Reads files the way `SimdMatcher`/`FuzzyMatcher` want them: a single
+/// contiguous byte slice, with no intermediate UTF-8 validation. Each
+/// buffered (non-mapped) read still allocates one `Arc<[u8]>` for the
+/// returned content.
+pub(crate) struct FileScanner {
+    /// Files at or above this size are memory-mapped; everything else uses
+    /// `line_buffer`.
+    mmap_threshold: u64,
+    /// Read target reused across calls for files under `mmap_threshold`,
+    /// pipes, and files that fail to map, so its capacity is kept between
+    /// reads; its contents are copied into a new `Arc<[u8]>` per call.
+    line_buffer: Vec<u8>,
+}
+
+impl FileScanner {
+    /// Create a scanner that maps files at or above `mmap_threshold` bytes.
+    pub(crate) fn new(mmap_threshold: u64) -> Self {
+        FileScanner {
+            mmap_threshold,
+            line_buffer: Vec::new(),
+        }
+    }
+
+    /// Reads `path`'s contents, memory-mapping it when it's large enough
+    /// and mappable, otherwise falling back to the reusable line buffer.
+    pub(crate) fn scan(&mut self, path: &Path) -> io::Result<FileContent> {
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+
+        if metadata.is_file() && metadata.len() > 0 && metadata.len() >= self.mmap_threshold {
+            // SAFETY: `Mmap::map` is unsafe because the file can be
+            // truncated or resized by another process after we map it,
+            // which would turn out-of-bounds reads into a SIGBUS instead of
+            // a normal I/O error. We can't detect a resize that happens
+            // *after* this point, but we do re-check the length
+            // immediately after mapping and fall back to a buffered read
+            // if it already moved under us, mirroring ripgrep's stance of
+            // not trusting a size that changed mid-open.
+            if let Ok(mmap) = unsafe { Mmap::map(&file) } {
+                let len_after_map = file.metadata().map(|m| m.len());
+                if len_after_map == Ok(metadata.len()) {
+                    return Ok(FileContent::Mapped(mmap));
+                }
+            }
+        }
+
+        self.line_buffer.clear();
+        let mut file = file;
+        file.read_to_end(&mut self.line_buffer)?;
+        Ok(FileContent::Buffered(Arc::from(
+            self.line_buffer.as_slice(),
+        )))
+    }
+
+    /// The size threshold above which files are memory-mapped.
+    pub(crate) fn mmap_threshold(&self) -> u64 {
+        self.mmap_threshold
+    }
+}
+
+impl Default for FileScanner {
+    fn default() -> Self {
+        Self::new(DEFAULT_MMAP_THRESHOLD_BYTES)
+    }
+}
+
+/// Quick binary-content check: bails as soon as a NUL byte appears in the
+/// first `BINARY_CHECK_WINDOW` bytes. Unlike `PathFilter::is_binary` in
+/// `optimizer.rs`, this doesn't special-case UTF-16 text -- `FileScanner`
+/// backs plain-text source search, not general file classification.
+pub(crate) fn looks_binary(content: &[u8]) -> bool {
+    let check_len = content.len().min(BINARY_CHECK_WINDOW);
+    content[..check_len].contains(&0)
+}
+
+/// Maps byte offsets within a file's content to 1-based line numbers and
+/// the matched line's own text, so a raw match offset from
+/// `SimdMatcher`/`FuzzyMatcher` can become `CachedResult::line`/
+/// `line_number`.
+pub(crate) struct LineIndex {
+    /// Byte offset where each line starts, in ascending order.
+    line_starts: Vec<usize>,
+    total_len: usize,
+}
+
+impl LineIndex {
+    /// Builds a line index over `content`. Correctly accounts for a final
+    /// line that isn't terminated by a newline.
+    pub(crate) fn build(content: &[u8]) -> Self {
+        let mut line_starts = vec![0];
+        for (i, &byte) in content.iter().enumerate() {
+            if byte == b'\n' {
+                line_starts.push(i + 1);
+            }
+        }
+
+        LineIndex {
+            line_starts,
+            total_len: content.len(),
+        }
+    }
+
+    /// Converts a byte offset into its (1-based line number, line text)
+    /// pair. The returned slice excludes the line's trailing `\n`, if any.
+    pub(crate) fn locate<'c>(&self, content: &'c [u8], byte_offset: usize) -> (usize, &'c [u8]) {
+        let line_idx = match self.line_starts.binary_search(&byte_offset) {
+            Ok(idx) => idx,
+            Err(idx) => idx.saturating_sub(1),
+        };
+
+        let start = self.line_starts[line_idx];
+        let end = self
+            .line_starts
+            .get(line_idx + 1)
+            .map(|&next_start| next_start - 1) // exclude this line's '\n'
+            .unwrap_or(self.total_len);
+
+        (line_idx + 1, &content[start..end])
+    }
+
+    /// Iterates every line in `content` as (1-based line number, line
+    /// text), excluding each line's trailing `\n`. A trailing newline at
+    /// the very end of `content` does not produce a spurious empty final
+    /// line.
+    pub(crate) fn iter_lines<'c>(
+        &self,
+        content: &'c [u8],
+    ) -> impl Iterator<Item = (usize, &'c [u8])> + '_ {
+        let ends_with_trailing_newline =
+            self.total_len > 0 && self.line_starts.last() == Some(&self.total_len);
+        let line_count = self.line_starts.len() - usize::from(ends_with_trailing_newline);
+
+        self.line_starts
+            .iter()
+            .take(line_count)
+            .enumerate()
+            .map(move |(idx, &start)| {
+                let end = self
+                    .line_starts
+                    .get(idx + 1)
+                    .map(|&next_start| next_start - 1)
+                    .unwrap_or(self.total_len);
+                (idx + 1, &content[start..end])
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_looks_binary_detects_nul_byte() {
+        assert!(looks_binary(b"ELF\x00\x01\x02"));
+        assert!(!looks_binary(b"fn main() {}"));
+    }
+
+    #[test]
+    fn test_looks_binary_empty_content() {
+        assert!(!looks_binary(b""));
+    }
+
+    #[test]
+    fn test_looks_binary_only_checks_leading_window() {
+        let mut content = vec![b'a'; BINARY_CHECK_WINDOW];
+        content.push(0); // NUL lands just past the checked window
+        assert!(!looks_binary(&content));
+    }
+
+    #[test]
+    fn test_line_index_basic() {
+        let content = b"first\nsecond\nthird";
+        let index = LineIndex::build(content);
+
+        let (line_no, line) = index.locate(content, 0);
+        assert_eq!((line_no, line), (1, &b"first"[..]));
+
+        let (line_no, line) = index.locate(content, 6);
+        assert_eq!((line_no, line), (2, &b"second"[..]));
+
+        let (line_no, line) = index.locate(content, 13);
+        assert_eq!((line_no, line), (3, &b"third"[..]));
+    }
+
+    #[test]
+    fn test_line_index_final_line_without_trailing_newline() {
+        let content = b"only one line, no trailing newline";
+        let index = LineIndex::build(content);
+
+        let (line_no, line) = index.locate(content, 5);
+        assert_eq!(line_no, 1);
+        assert_eq!(line, &content[..]);
+    }
+
+    #[test]
+    fn test_line_index_trailing_newline_does_not_add_phantom_line() {
+        let content = b"abc\ndef\n";
+        let index = LineIndex::build(content);
+
+        let (line_no, line) = index.locate(content, 4);
+        assert_eq!((line_no, line), (2, &b"def"[..]));
+    }
+
+    #[test]
+    fn test_line_index_iter_lines() {
+        let content = b"first\nsecond\nthird";
+        let index = LineIndex::build(content);
+        let lines: Vec<(usize, &[u8])> = index.iter_lines(content).collect();
+        assert_eq!(
+            lines,
+            vec![(1, &b"first"[..]), (2, &b"second"[..]), (3, &b"third"[..])]
+        );
+    }
+
+    #[test]
+    fn test_line_index_iter_lines_no_phantom_trailing_line() {
+        let content = b"abc\ndef\n";
+        let index = LineIndex::build(content);
+        let lines: Vec<(usize, &[u8])> = index.iter_lines(content).collect();
+        assert_eq!(lines, vec![(1, &b"abc"[..]), (2, &b"def"[..])]);
+    }
+
+    #[test]
+    fn test_line_index_mid_line_offset_resolves_to_that_line() {
+        let content = b"hello world\nsecond line";
+        let index = LineIndex::build(content);
+
+        // Offset inside "world", not at a line start.
+        let (line_no, line) = index.locate(content, 6);
+        assert_eq!((line_no, line), (1, &b"hello world"[..]));
+    }
+
+    #[test]
+    fn test_file_scanner_small_file_uses_buffer() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("fluid_grep_scan_test_small_{}", std::process::id()));
+        std::fs::write(&path, b"small file contents").unwrap();
+
+        let mut scanner = FileScanner::new(1024 * 1024);
+        let content = scanner.scan(&path).unwrap();
+        assert!(matches!(content, FileContent::Buffered(_)));
+        assert_eq!(content.as_bytes(), b"small file contents");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_scanner_large_file_uses_mmap() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("fluid_grep_scan_test_large_{}", std::process::id()));
+        {
+            let mut file = std::fs::File::create(&path).unwrap();
+            let big = vec![b'x'; 128 * 1024];
+            file.write_all(&big).unwrap();
+        }
+
+        let mut scanner = FileScanner::new(64 * 1024);
+        let content = scanner.scan(&path).unwrap();
+        assert!(matches!(content, FileContent::Mapped(_)));
+        assert_eq!(content.as_bytes().len(), 128 * 1024);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_scanner_reuses_buffer_across_calls() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("fluid_grep_scan_test_reuse_{}", std::process::id()));
+        std::fs::write(&path, b"first pass").unwrap();
+
+        let mut scanner = FileScanner::new(1024 * 1024);
+        {
+            let content = scanner.scan(&path).unwrap();
+            assert_eq!(content.as_bytes(), b"first pass");
+        }
+
+        std::fs::write(&path, b"second pass, different length").unwrap();
+        let content = scanner.scan(&path).unwrap();
+        assert_eq!(content.as_bytes(), b"second pass, different length");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
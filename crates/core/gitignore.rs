@@ -0,0 +1,338 @@
+/*!
+Gitignore-style ignore file parsing and glob matching.
+
+`PathFilter` only knew about a fixed/configurable set of substrings to
+skip (see `optimizer::AhoCorasick`). This module adds the other half of
+what a real tree walker needs: honoring `.gitignore`/`.ignore` files
+discovered along the way, with the same glob syntax git itself uses
+(`*`, `**`, `?`, leading `!` negation, and anchored/trailing-slash
+semantics), plus a project-level filter file that can pull in shared
+rule sets via `%include <path>` (evaluated recursively, Mercurial config
+style) so a team can centralize ignore rules instead of copy-pasting
+them into every repo.
+
+Each `.gitignore`-style file becomes one `IgnoreMatcher`; `PathFilter`
+can hold several, layered in the order they're discovered while walking
+(root-level first, more specific ones after), since git itself resolves
+conflicts in favor of the most specific matching rule.
+*/
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// One compiled rule from an ignore file.
+#[derive(Clone, Debug)]
+struct IgnoreRule {
+    /// Glob pattern, already anchored to the root (prefixed with `**/`
+    /// at compile time if the source line wasn't itself anchored).
+    pattern: String,
+    /// `true` for a `!`-prefixed line: a later match re-includes a path
+    /// an earlier rule ignored.
+    negate: bool,
+    /// `true` if the source line had a trailing `/`: only matches
+    /// directories.
+    dir_only: bool,
+}
+
+/// A set of ignore rules parsed from one `.gitignore`/`.ignore`-style
+/// file (or an inline list of pattern lines), applied with git's own
+/// "last matching rule wins" semantics.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct IgnoreMatcher {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreMatcher {
+    pub(crate) fn new() -> Self {
+        IgnoreMatcher { rules: Vec::new() }
+    }
+
+    /// Parses every line from `lines` as a gitignore-style rule, in
+    /// order.
+    pub(crate) fn from_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut matcher = IgnoreMatcher::new();
+        for line in lines {
+            matcher.add_line(line);
+        }
+        matcher
+    }
+
+    /// Parses and adds one ignore-file line. Blank lines and `#`
+    /// comments are ignored, matching git's own format.
+    pub(crate) fn add_line(&mut self, raw_line: &str) {
+        let line = raw_line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+
+        let (line, negate) = match line.strip_prefix('!') {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if line.is_empty() {
+            return;
+        }
+
+        let (line, dir_only) = match line.strip_suffix('/') {
+            Some(rest) => (rest, true),
+            None => (line, false),
+        };
+        if line.is_empty() {
+            return;
+        }
+
+        let explicitly_anchored = line.starts_with('/');
+        let stripped = line.strip_prefix('/').unwrap_or(line);
+        // A slash anywhere but the very end means the pattern is rooted
+        // at the ignore file's directory; no internal slash at all means
+        // it can match at any depth below it, per gitignore's own rule.
+        let anchored = explicitly_anchored || stripped.contains('/');
+
+        let pattern = if anchored {
+            stripped.to_string()
+        } else {
+            format!("**/{}", stripped)
+        };
+
+        self.rules.push(IgnoreRule {
+            pattern,
+            negate,
+            dir_only,
+        });
+    }
+
+    /// Whether `relative_path` (forward-slash separated, relative to
+    /// this matcher's root) is ignored, applying the last matching
+    /// rule's verdict. Returns `None` when no rule in this matcher
+    /// touched the path at all, so callers layering several matchers can
+    /// tell "not mentioned here" apart from "explicitly re-included".
+    pub(crate) fn is_ignored(&self, relative_path: &str, is_dir: bool) -> Option<bool> {
+        let text_segments: Vec<&str> = relative_path.split('/').collect();
+        let mut verdict = None;
+        for rule in &self.rules {
+            if rule_matches(rule, &text_segments, is_dir) {
+                verdict = Some(!rule.negate);
+            }
+        }
+        verdict
+    }
+}
+
+/// Whether `rule` matches `text_segments` itself, or matches one of its
+/// ancestor directories -- ignoring a directory implicitly ignores
+/// everything below it, so `node_modules` must match not just a bare
+/// `node_modules` path but also `a/b/node_modules/pkg/file.js`. Only the
+/// full-path case needs the `is_dir` check: any shorter, ancestor-level
+/// match is necessarily a directory by construction.
+fn rule_matches(rule: &IgnoreRule, text_segments: &[&str], is_dir: bool) -> bool {
+    let pattern_segments: Vec<&str> = rule.pattern.split('/').collect();
+    for prefix_len in 1..=text_segments.len() {
+        let is_full_path = prefix_len == text_segments.len();
+        if rule.dir_only && is_full_path && !is_dir {
+            continue;
+        }
+        if match_segments(&pattern_segments, &text_segments[..prefix_len]) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Reads `path` as a gitignore-style ignore file and returns its pattern
+/// lines (comments/blanks included verbatim -- `IgnoreMatcher` filters
+/// those), resolving any `%include <path>` directive recursively and
+/// splicing the included file's lines in at that point, so includes
+/// declared near the top of a file end up merged before the file's own
+/// local rules. `<path>` is resolved relative to the including file's
+/// directory. A file that (directly or transitively) includes itself is
+/// only ever read once, to stay terminating.
+pub(crate) fn read_ignore_file_patterns(path: &Path) -> Result<Vec<String>> {
+    let mut visited = HashSet::new();
+    let mut patterns = Vec::new();
+    collect_patterns(path, &mut visited, &mut patterns)?;
+    Ok(patterns)
+}
+
+fn collect_patterns(
+    path: &Path,
+    visited: &mut HashSet<PathBuf>,
+    out: &mut Vec<String>,
+) -> Result<()> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if !visited.insert(canonical) {
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("reading ignore file {}", path.display()))?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    for line in content.lines() {
+        match line.trim_end().strip_prefix("%include ") {
+            Some(included) => {
+                let include_path = base_dir.join(included.trim());
+                collect_patterns(&include_path, visited, out)?;
+            }
+            None => out.push(line.to_string()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Matches a full (already `/`-split) path against a (already `/`-split)
+/// pattern, supporting `*` (any run of characters within one path
+/// segment), `?` (exactly one character within one segment), and `**`
+/// (zero or more whole path segments).
+fn match_segments(pattern: &[&str], text: &[&str]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(&"**") => {
+            if pattern.len() == 1 {
+                return true;
+            }
+            (0..=text.len()).any(|skip| match_segments(&pattern[1..], &text[skip..]))
+        }
+        Some(&segment_pattern) => {
+            !text.is_empty()
+                && segment_match(segment_pattern, text[0])
+                && match_segments(&pattern[1..], &text[1..])
+        }
+    }
+}
+
+/// Matches a single path segment (no `/` in either argument) against a
+/// pattern that may contain `*` and `?`.
+fn segment_match(pattern: &str, text: &str) -> bool {
+    let pattern_chars: Vec<char> = pattern.chars().collect();
+    let text_chars: Vec<char> = text.chars().collect();
+    segment_match_chars(&pattern_chars, &text_chars)
+}
+
+fn segment_match_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => (0..=text.len()).any(|skip| segment_match_chars(&pattern[1..], &text[skip..])),
+        Some('?') => !text.is_empty() && segment_match_chars(&pattern[1..], &text[1..]),
+        Some(&c) => !text.is_empty() && text[0] == c && segment_match_chars(&pattern[1..], &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_pattern_matches_any_depth() {
+        let matcher = IgnoreMatcher::from_lines(["node_modules"]);
+        assert_eq!(matcher.is_ignored("node_modules", true), Some(true));
+        assert_eq!(
+            matcher.is_ignored("a/b/node_modules/pkg/file.js", false),
+            Some(true)
+        );
+        assert_eq!(matcher.is_ignored("src/main.rs", false), None);
+    }
+
+    #[test]
+    fn test_anchored_pattern_only_matches_from_root() {
+        let matcher = IgnoreMatcher::from_lines(["/build"]);
+        assert_eq!(matcher.is_ignored("build", true), Some(true));
+        assert_eq!(matcher.is_ignored("vendor/build", true), None);
+    }
+
+    #[test]
+    fn test_internal_slash_without_leading_slash_is_still_anchored() {
+        let matcher = IgnoreMatcher::from_lines(["src/generated"]);
+        assert_eq!(matcher.is_ignored("src/generated", true), Some(true));
+        assert_eq!(matcher.is_ignored("lib/src/generated", true), None);
+    }
+
+    #[test]
+    fn test_trailing_slash_only_matches_directories() {
+        let matcher = IgnoreMatcher::from_lines(["logs/"]);
+        assert_eq!(matcher.is_ignored("logs", true), Some(true));
+        assert_eq!(matcher.is_ignored("logs", false), None);
+    }
+
+    #[test]
+    fn test_negation_reincludes_after_earlier_ignore() {
+        let matcher = IgnoreMatcher::from_lines(["*.log", "!important.log"]);
+        assert_eq!(matcher.is_ignored("debug.log", false), Some(true));
+        assert_eq!(matcher.is_ignored("important.log", false), Some(false));
+    }
+
+    #[test]
+    fn test_last_matching_rule_wins() {
+        let matcher = IgnoreMatcher::from_lines(["*.txt", "!keep.txt", "keep.txt"]);
+        assert_eq!(matcher.is_ignored("keep.txt", false), Some(true));
+    }
+
+    #[test]
+    fn test_double_star_matches_nested_directories() {
+        let matcher = IgnoreMatcher::from_lines(["**/fixtures/**"]);
+        assert_eq!(
+            matcher.is_ignored("a/b/fixtures/c/d.json", false),
+            Some(true)
+        );
+        assert_eq!(matcher.is_ignored("fixtures/d.json", false), Some(true));
+    }
+
+    #[test]
+    fn test_question_mark_matches_exactly_one_char() {
+        let matcher = IgnoreMatcher::from_lines(["file?.txt"]);
+        assert_eq!(matcher.is_ignored("file1.txt", false), Some(true));
+        assert_eq!(matcher.is_ignored("file12.txt", false), None);
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines_are_ignored() {
+        let matcher = IgnoreMatcher::from_lines(["# a comment", "", "*.o"]);
+        assert_eq!(matcher.is_ignored("main.o", false), Some(true));
+    }
+
+    #[test]
+    fn test_include_directive_merges_patterns_recursively() {
+        let dir = std::env::temp_dir().join(format!(
+            "fluid-grep-gitignore-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let shared_path = dir.join("shared-ignore");
+        fs::write(&shared_path, "*.shared\n").unwrap();
+
+        let project_path = dir.join(".projectignore");
+        fs::write(&project_path, "%include shared-ignore\n*.local\n").unwrap();
+
+        let patterns = read_ignore_file_patterns(&project_path).unwrap();
+        let matcher = IgnoreMatcher::from_lines(patterns.iter().map(String::as_str));
+
+        assert_eq!(matcher.is_ignored("build.shared", false), Some(true));
+        assert_eq!(matcher.is_ignored("build.local", false), Some(true));
+        assert_eq!(matcher.is_ignored("build.rs", false), None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_self_including_file_does_not_infinite_loop() {
+        let dir = std::env::temp_dir().join(format!(
+            "fluid-grep-gitignore-cycle-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join(".projectignore");
+        fs::write(&path, "%include .projectignore\n*.local\n").unwrap();
+
+        let patterns = read_ignore_file_patterns(&path).unwrap();
+        assert_eq!(patterns, vec!["*.local".to_string()]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -9,6 +9,8 @@ to a 0-1000 range for practical use. This ensures fair comparison across
 different pattern lengths and match types.
 */
 
+use serde::Serialize;
+
 /// Customizable weights for heuristic scoring.
 /// All weights should be between 0.0 and 1.0.
 #[derive(Clone, Debug)]
@@ -99,6 +101,91 @@ pub(crate) struct HeuristicConfig {
     pub consecutive_match_bonus: f32,
     /// Custom scoring weights. Default: standard weights
     pub weights: ScoringWeights,
+    /// Characters treated as word delimiters for `CharClass::Delimiter`
+    /// and the post-delimiter positional bonus. Default: `_ - / .`
+    pub delimiters: Vec<char>,
+    /// Bonus for a matched character that begins a word (text start, or
+    /// the previous character is whitespace/non-word). Default: 0.3
+    pub word_start_bonus: f32,
+    /// Bonus for a matched character at a camelCase transition (previous
+    /// character lowercase, this one uppercase). Default: 0.2
+    pub camel_case_bonus: f32,
+    /// Bonus for a matched character immediately following a delimiter.
+    /// Default: 0.15
+    pub delimiter_bonus: f32,
+    /// Whether to fold accented/decomposed Latin characters to their ASCII
+    /// base form before matching, so e.g. pattern `cafe` reaches `café`.
+    /// Default: false
+    pub normalize: bool,
+    /// Penalty subtracted from the raw alignment score, in
+    /// `optimal_fuzzy_score`, for each hole (maximal gap between
+    /// consecutive matched characters). Default: 6.0
+    pub hole_penalty: f32,
+    /// Extra penalty for a matched character whose both neighbors are
+    /// unmatched text -- an isolated single-character hit that is neither
+    /// the first nor the last match. Default: 4.0
+    pub isolated_char_penalty: f32,
+    /// Override for the maximum number of holes a fuzzy alignment may have
+    /// before `optimal_fuzzy_score` rejects it outright. `None` derives the
+    /// cap from pattern length via `max_holes_for_pattern_len`. Default: None
+    pub max_holes: Option<usize>,
+    /// Whether `is_close_match` should count an adjacent-character swap
+    /// (e.g. "teh" vs "the") as a single edit via Damerau-Levenshtein,
+    /// rather than the two substitutions a plain Levenshtein distance
+    /// would charge for it. Default: true
+    pub allow_transpositions: bool,
+    /// When true, `calculate_relevance_score_with_config` scores through
+    /// `score_span` (a Selecta-style tightest-subsequence-window density
+    /// score) instead of the additive `ScoringWeights` model, rewarding
+    /// clustering and shorter candidates over the usual per-factor bonus
+    /// sum. Default: false
+    pub span_scoring: bool,
+    /// When true, `calculate_relevance_score_with_config` scores through
+    /// `positional_bonus_score` (a greedy fzf v1-style positional scan)
+    /// instead of the additive `ScoringWeights` model. Takes priority over
+    /// `span_scoring` if both are set. Default: false
+    pub positional_bonus_scoring: bool,
+    /// Bonus added by `positional_bonus_score` for a matched character at
+    /// a word boundary -- preceded by a delimiter, whitespace, or the
+    /// start of the text -- or a camelCase transition. Default: 8.0
+    pub fzf_boundary_bonus: f32,
+    /// One-time bonus `positional_bonus_score` adds for matching the very
+    /// first pattern character. Default: 8.0
+    pub fzf_first_char_bonus: f32,
+    /// Bonus `positional_bonus_score` adds per matched character that
+    /// continues an unbroken run with no gap since the previous match.
+    /// Default: 4.0
+    pub fzf_consecutive_bonus: f32,
+    /// Penalty `positional_bonus_score` charges the first time a run
+    /// breaks -- the first text character skipped since the previous
+    /// match. Default: 3.0
+    pub fzf_gap_start_penalty: f32,
+    /// Smaller penalty `positional_bonus_score` charges for each
+    /// additional skipped character once a gap is already open.
+    /// Default: 1.0
+    pub fzf_gap_extension_penalty: f32,
+    /// Penalty `positional_bonus_score` charges when a lowercase pattern
+    /// character matches an uppercase text character. Default: 3.0
+    pub fzf_case_mismatch_penalty: f32,
+    /// Whether `rank_candidates`/`rank_candidates_detailed` compute
+    /// `ScoredMatch::word_position_sum`, a MeiliSearch-style
+    /// `SumOfWordsPosition` tie-break that ranks a candidate higher when
+    /// its matched query terms appear in earlier words. Off by default
+    /// since it only makes sense for multi-token identifier/title
+    /// matching (e.g. `get_user_name`), not free-form path matching.
+    /// Default: false
+    pub word_position_tiebreak: bool,
+    /// Maximum total word-index displacement `phrase_proximity` tolerates
+    /// between a multi-word query's matched words before it stops
+    /// contributing a proximity bonus at all. Mirrors tantivy's phrase
+    /// query `slop`. Default: 4
+    pub slop: usize,
+    /// Weight of the bidirectional-slop phrase proximity bonus added to
+    /// `calculate_relevance_score_with_config`'s score -- full weight
+    /// when a multi-word query's words are adjacent in `text`, scaled
+    /// down to zero as their minimal total displacement approaches
+    /// `slop`. Default: 0.15
+    pub proximity_weight: f32,
 }
 
 impl HeuristicConfig {
@@ -117,6 +204,26 @@ impl HeuristicConfig {
             case_sensitive_substring,
             consecutive_match_bonus,
             weights: ScoringWeights::default(),
+            delimiters: default_delimiters(),
+            word_start_bonus: 0.3,
+            camel_case_bonus: 0.2,
+            delimiter_bonus: 0.15,
+            normalize: false,
+            hole_penalty: 6.0,
+            isolated_char_penalty: 4.0,
+            max_holes: None,
+            allow_transpositions: true,
+            span_scoring: false,
+            positional_bonus_scoring: false,
+            fzf_boundary_bonus: 8.0,
+            fzf_first_char_bonus: 8.0,
+            fzf_consecutive_bonus: 4.0,
+            fzf_gap_start_penalty: 3.0,
+            fzf_gap_extension_penalty: 1.0,
+            fzf_case_mismatch_penalty: 3.0,
+            word_position_tiebreak: false,
+            slop: 4,
+            proximity_weight: 0.15,
         }
     }
 
@@ -136,10 +243,35 @@ impl HeuristicConfig {
             case_sensitive_substring,
             consecutive_match_bonus,
             weights,
+            delimiters: default_delimiters(),
+            word_start_bonus: 0.3,
+            camel_case_bonus: 0.2,
+            delimiter_bonus: 0.15,
+            normalize: false,
+            hole_penalty: 6.0,
+            isolated_char_penalty: 4.0,
+            max_holes: None,
+            allow_transpositions: true,
+            span_scoring: false,
+            positional_bonus_scoring: false,
+            fzf_boundary_bonus: 8.0,
+            fzf_first_char_bonus: 8.0,
+            fzf_consecutive_bonus: 4.0,
+            fzf_gap_start_penalty: 3.0,
+            fzf_gap_extension_penalty: 1.0,
+            fzf_case_mismatch_penalty: 3.0,
+            word_position_tiebreak: false,
+            slop: 4,
+            proximity_weight: 0.15,
         }
     }
 }
 
+/// Default delimiter set for `HeuristicConfig::delimiters`.
+fn default_delimiters() -> Vec<char> {
+    vec!['_', '-', '/', '.']
+}
+
 impl Default for HeuristicConfig {
     fn default() -> Self {
         HeuristicConfig {
@@ -149,6 +281,26 @@ impl Default for HeuristicConfig {
             case_sensitive_substring: false,
             consecutive_match_bonus: 1.0,
             weights: ScoringWeights::default(),
+            delimiters: default_delimiters(),
+            word_start_bonus: 0.3,
+            camel_case_bonus: 0.2,
+            delimiter_bonus: 0.15,
+            normalize: false,
+            hole_penalty: 6.0,
+            isolated_char_penalty: 4.0,
+            max_holes: None,
+            allow_transpositions: true,
+            span_scoring: false,
+            positional_bonus_scoring: false,
+            fzf_boundary_bonus: 8.0,
+            fzf_first_char_bonus: 8.0,
+            fzf_consecutive_bonus: 4.0,
+            fzf_gap_start_penalty: 3.0,
+            fzf_gap_extension_penalty: 1.0,
+            fzf_case_mismatch_penalty: 3.0,
+            word_position_tiebreak: false,
+            slop: 4,
+            proximity_weight: 0.15,
         }
     }
 }
@@ -160,12 +312,32 @@ pub(crate) struct ScoredMatch {
     pub text: String,
     /// The relevance score (0-1000)
     pub score: u32,
+    /// Byte offsets of the matched characters in `text`, from
+    /// `optimal_fuzzy_score`, for highlighting. Empty for an empty
+    /// pattern, which matches without any aligned characters.
+    pub positions: Vec<usize>,
+    /// MeiliSearch-style `SumOfWordsPosition` tie-break: the sum, over
+    /// every query term matched in `text` (counting only each term's
+    /// earliest matched word), of that word's index after tokenizing
+    /// `text` on whitespace and `HeuristicConfig::delimiters`. Lower sums
+    /// mean the query terms appeared earlier, and rank higher. `None`
+    /// when `HeuristicConfig::word_position_tiebreak` is off (the
+    /// default), in which case `Ord` falls back to comparing `score`
+    /// alone.
+    pub word_position_sum: Option<usize>,
 }
 
 impl Ord for ScoredMatch {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // Sort by score descending (higher scores first)
-        other.score.cmp(&self.score)
+        // Sort by score descending (higher scores first), then by
+        // word_position_sum ascending (earlier query terms first) when
+        // both sides have one -- ties on score without it just stay tied.
+        other.score.cmp(&self.score).then_with(|| {
+            match (self.word_position_sum, other.word_position_sum) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                _ => std::cmp::Ordering::Equal,
+            }
+        })
     }
 }
 
@@ -185,9 +357,159 @@ pub(crate) struct ScoreBreakdown {
     pub fuzzy_match: f32,
     pub substring_match: f32,
     pub length_similarity: f32,
+    /// Contribution from matched characters that begin a word.
+    pub word_start: f32,
+    /// Contribution from matched characters at a camelCase transition.
+    pub camel_case: f32,
+    /// Contribution from matched characters following a delimiter.
+    pub delimiter_match: f32,
     pub total: u32,
 }
 
+/// A single scoring factor's contribution to a candidate's final score,
+/// following the MeiliSearch score-details pattern: the unweighted
+/// `raw` sub-score this factor produced, the `weighted` amount actually
+/// added to the total (`raw * weight`), and a `local_score` in `[0, 1]`
+/// so UIs can render per-factor bars independent of the weight scale.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub(crate) struct FactorScore {
+    pub raw: f32,
+    pub weighted: f32,
+    pub local_score: f32,
+}
+
+impl FactorScore {
+    fn new(raw: f32, weight: f32) -> Self {
+        FactorScore {
+            raw,
+            weighted: raw * weight,
+            local_score: raw.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Per-factor breakdown of a candidate's relevance score, one
+/// [`FactorScore`] per [`ScoringWeights`] field. Lets callers explain why
+/// a candidate ranked where it did, or re-sort on a secondary factor,
+/// without reimplementing the scorer. Serializes to a JSON map (via
+/// `serde_json::to_value` or similar) so external tools can render
+/// per-factor bars.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ScoreDetails {
+    pub exact_match: FactorScore,
+    pub case_sensitive: FactorScore,
+    pub word_boundary: FactorScore,
+    pub fuzzy_match: FactorScore,
+    pub substring_match: FactorScore,
+    pub length_similarity: FactorScore,
+}
+
+impl ScoreDetails {
+    /// Sum of every factor's weighted contribution. Matches
+    /// `calculate_relevance_score_with_config`'s score before the
+    /// char-class positional bonuses (word-start/camelCase/delimiter) and
+    /// the phrase proximity bonus are added in, since those aren't part of
+    /// `ScoringWeights`.
+    pub(crate) fn weighted_total(&self) -> f32 {
+        self.exact_match.weighted
+            + self.case_sensitive.weighted
+            + self.word_boundary.weighted
+            + self.fuzzy_match.weighted
+            + self.substring_match.weighted
+            + self.length_similarity.weighted
+    }
+}
+
+/// Broad lexical class of a single character of candidate text, used to
+/// decide which positional bonus (if any) a matched character earns.
+/// Borrowed from fzf/nucleo's char-class model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CharClass {
+    Lower,
+    Upper,
+    Number,
+    Whitespace,
+    Delimiter,
+    NonWord,
+}
+
+/// Classifies `ch`, treating any character in `delimiters` as
+/// `CharClass::Delimiter` before falling back to general Unicode
+/// category checks.
+pub(crate) fn classify_char(ch: char, delimiters: &[char]) -> CharClass {
+    if delimiters.contains(&ch) {
+        CharClass::Delimiter
+    } else if ch.is_whitespace() {
+        CharClass::Whitespace
+    } else if ch.is_numeric() {
+        CharClass::Number
+    } else if ch.is_uppercase() {
+        CharClass::Upper
+    } else if ch.is_lowercase() {
+        CharClass::Lower
+    } else {
+        CharClass::NonWord
+    }
+}
+
+/// Sums the three position-dependent char-class bonuses over every
+/// character of `pattern` matched in `text` (using the alignment found by
+/// `optimal_fuzzy_score`), returning `(word_start, camel_case,
+/// delimiter_match)` each already scaled into the same 0.0-<weight> range
+/// the other `ScoreBreakdown` fields use: the fraction of matched
+/// characters earning that bonus, times its configured magnitude.
+///
+/// The three bonuses are mutually exclusive per matched character: a
+/// character at the very start of the text, or right after
+/// whitespace/other non-word text, is a word start; right after a
+/// lowercase-to-uppercase transition it's a camelCase hit; right after a
+/// configured delimiter it's a delimiter hit. This lets `fb` rank
+/// `FooBar` (word-start `F`, camelCase `B`) above `affable` (`f`/`b`
+/// buried mid-word, neither bonus applies), which a single flat
+/// `word_boundary` bonus can't express.
+fn char_class_bonuses(pattern: &str, text: &str, config: &HeuristicConfig) -> (f32, f32, f32) {
+    let Some((_, positions)) = optimal_fuzzy_score(pattern, text, config) else {
+        return (0.0, 0.0, 0.0);
+    };
+    if positions.is_empty() {
+        return (0.0, 0.0, 0.0);
+    }
+
+    let text_chars: Vec<char> = text.chars().collect();
+    let char_byte_offsets: Vec<usize> = text.char_indices().map(|(b, _)| b).collect();
+
+    let mut word_start_hits = 0usize;
+    let mut camel_hits = 0usize;
+    let mut delimiter_hits = 0usize;
+
+    for &byte_pos in &positions {
+        let Some(idx) = char_byte_offsets.iter().position(|&b| b == byte_pos) else {
+            continue;
+        };
+
+        if idx == 0 {
+            word_start_hits += 1;
+            continue;
+        }
+
+        let prev_class = classify_char(text_chars[idx - 1], &config.delimiters);
+        if prev_class == CharClass::Delimiter {
+            delimiter_hits += 1;
+        } else if prev_class == CharClass::Lower && text_chars[idx].is_uppercase() {
+            camel_hits += 1;
+        } else if matches!(prev_class, CharClass::Whitespace | CharClass::NonWord) {
+            word_start_hits += 1;
+        }
+    }
+
+    let total = positions.len() as f32;
+    (
+        (word_start_hits as f32 / total) * config.word_start_bonus,
+        (camel_hits as f32 / total) * config.camel_case_bonus,
+        (delimiter_hits as f32 / total) * config.delimiter_bonus,
+    )
+}
+
 /// Calculates a normalized relevance score (0-1000) for a match.
 /// Higher score = better match.
 ///
@@ -221,6 +543,16 @@ pub(crate) fn calculate_relevance_score_with_config(
     is_case_sensitive: bool,
     config: &HeuristicConfig,
 ) -> u32 {
+    if config.positional_bonus_scoring {
+        return positional_bonus_score(pattern, text, config)
+            .map(|(score, _positions)| score)
+            .unwrap_or(0);
+    }
+
+    if config.span_scoring {
+        return (score_span(pattern, text) * 1000.0).min(1000.0) as u32;
+    }
+
     let mut score = 0.0f32;
 
     // Exact match bonus (highest priority)
@@ -249,13 +581,18 @@ pub(crate) fn calculate_relevance_score_with_config(
         score += 0.3;
     }
 
-    // Fuzzy/consecutive character match bonus
-    if fuzzy_match_with_threshold(pattern, text, config.fuzzy_threshold) {
-        score += 0.2;
+    // Fuzzy/consecutive character match bonus, graded by alignment quality
+    // rather than a flat bonus for any match above threshold.
+    if let Some((optimal_score, _positions)) = optimal_fuzzy_score(pattern, text, config) {
+        score += (optimal_score as f32 / 1000.0) * 0.2;
     }
 
+    // Char-class positional bonuses (word-start, camelCase, post-delimiter)
+    let (word_start, camel_case, delimiter_match) = char_class_bonuses(pattern, text, config);
+    score += word_start + camel_case + delimiter_match;
+
     // Substring match bonus
-    if is_substring_match(pattern, text, config.case_sensitive_substring) {
+    if is_substring_match(pattern, text, config.case_sensitive_substring, config.normalize) {
         score += 0.15;
     }
 
@@ -263,6 +600,15 @@ pub(crate) fn calculate_relevance_score_with_config(
     let length_score = calculate_length_similarity(pattern, text);
     score += length_score * 0.1;
 
+    // Bidirectional-slop phrase proximity bonus for multi-word queries --
+    // `None` (single-word pattern, a query word absent from `text`, or a
+    // gap past `slop`) contributes nothing.
+    if let Some(gap) = phrase_proximity(pattern, text, &config.delimiters, config.normalize, config.slop)
+    {
+        let closeness = 1.0 - (gap as f32 / config.slop.max(1) as f32);
+        score += closeness.max(0.0) * config.proximity_weight;
+    }
+
     // Scale to 0-1000 range
     (score * 1000.0).min(1000.0) as u32
 }
@@ -282,6 +628,9 @@ pub(crate) fn calculate_relevance_score_breakdown(
         fuzzy_match: 0.0,
         substring_match: 0.0,
         length_similarity: 0.0,
+        word_start: 0.0,
+        camel_case: 0.0,
+        delimiter_match: 0.0,
         total: 0,
     };
 
@@ -302,11 +651,16 @@ pub(crate) fn calculate_relevance_score_breakdown(
         breakdown.word_boundary = 0.3;
     }
 
-    if fuzzy_match_with_threshold(pattern, text, config.fuzzy_threshold) {
-        breakdown.fuzzy_match = 0.2;
+    if let Some((optimal_score, _positions)) = optimal_fuzzy_score(pattern, text, config) {
+        breakdown.fuzzy_match = (optimal_score as f32 / 1000.0) * 0.2;
     }
 
-    if is_substring_match(pattern, text, config.case_sensitive_substring) {
+    let (word_start, camel_case, delimiter_match) = char_class_bonuses(pattern, text, config);
+    breakdown.word_start = word_start;
+    breakdown.camel_case = camel_case;
+    breakdown.delimiter_match = delimiter_match;
+
+    if is_substring_match(pattern, text, config.case_sensitive_substring, config.normalize) {
         breakdown.substring_match = 0.15;
     }
 
@@ -316,6 +670,9 @@ pub(crate) fn calculate_relevance_score_breakdown(
         + breakdown.case_sensitive
         + breakdown.word_boundary
         + breakdown.fuzzy_match
+        + breakdown.word_start
+        + breakdown.camel_case
+        + breakdown.delimiter_match
         + breakdown.substring_match
         + breakdown.length_similarity;
 
@@ -323,33 +680,179 @@ pub(crate) fn calculate_relevance_score_breakdown(
     breakdown
 }
 
+/// Returns a [`ScoreDetails`] breakdown of a candidate's score, one
+/// [`FactorScore`] per [`ScoringWeights`] factor, so callers can explain a
+/// ranking or re-sort on a specific factor. The weight each factor is
+/// scaled by mirrors `calculate_relevance_score_with_config`'s hardcoded
+/// constants (the same ones `ScoringWeights::default()` holds), so
+/// `ScoreDetails::weighted_total` reconstructs that function's score
+/// modulo the char-class positional bonuses and the phrase proximity
+/// bonus, which aren't `ScoringWeights` factors.
+pub(crate) fn calculate_relevance_score_details(
+    pattern: &str,
+    text: &str,
+    is_exact: bool,
+    is_case_sensitive: bool,
+    config: &HeuristicConfig,
+) -> ScoreDetails {
+    let exact_match = FactorScore::new(if is_exact { 1.0 } else { 0.0 }, 1.0);
+
+    let case_sensitive = if !is_case_sensitive {
+        FactorScore::new(0.0, 0.5)
+    } else if pattern == text {
+        FactorScore::new(1.0, 0.5)
+    } else {
+        let case_matches = pattern
+            .chars()
+            .zip(text.chars())
+            .filter(|(p, t)| p == t)
+            .count();
+        let case_ratio = case_matches as f32 / pattern.len().max(1) as f32;
+        FactorScore::new(case_ratio * 0.5, 0.5)
+    };
+
+    let word_boundary = FactorScore::new(
+        if is_word_boundary_match(pattern, text, config.unicode_aware) {
+            1.0
+        } else {
+            0.0
+        },
+        0.3,
+    );
+
+    let fuzzy_raw = optimal_fuzzy_score(pattern, text, config)
+        .map(|(optimal_score, _positions)| optimal_score as f32 / 1000.0)
+        .unwrap_or(0.0);
+    let fuzzy_match = FactorScore::new(fuzzy_raw, 0.2);
+
+    let substring_match = FactorScore::new(
+        if is_substring_match(pattern, text, config.case_sensitive_substring, config.normalize) {
+            1.0
+        } else {
+            0.0
+        },
+        0.15,
+    );
+
+    let length_similarity =
+        FactorScore::new(calculate_length_similarity(pattern, text), 0.1);
+
+    ScoreDetails {
+        exact_match,
+        case_sensitive,
+        word_boundary,
+        fuzzy_match,
+        substring_match,
+        length_similarity,
+    }
+}
+
+/// Char-indexed view of a string, for correctness on multibyte UTF-8.
+/// Pre-collects each character alongside the byte offset span it occupies
+/// in the original text, so matching logic can index characters in O(1)
+/// and convert back to byte ranges only at the boundary (e.g. when
+/// building a highlighted `String`), instead of assuming one byte per
+/// character -- an assumption that produces wrong offsets, or a byte
+/// index that isn't even a char boundary, for CJK, accented, or emoji
+/// text. Pure-ASCII callers should prefer a byte-indexed fast path
+/// instead, since there char and byte indices always coincide.
+pub(crate) struct Utf32 {
+    chars: Vec<char>,
+    byte_starts: Vec<usize>,
+}
+
+impl Utf32 {
+    pub(crate) fn new(text: &str) -> Self {
+        let mut chars = Vec::with_capacity(text.len());
+        let mut byte_starts = Vec::with_capacity(text.len() + 1);
+        for (start, c) in text.char_indices() {
+            byte_starts.push(start);
+            chars.push(c);
+        }
+        byte_starts.push(text.len());
+
+        Utf32 { chars, byte_starts }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub(crate) fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// Byte range in the original text spanned by character indices
+    /// `[start, end)`.
+    pub(crate) fn byte_range(&self, start: usize, end: usize) -> (usize, usize) {
+        (self.byte_starts[start], self.byte_starts[end])
+    }
+}
+
+/// Returns the character index of the first occurrence of `pattern_chars`
+/// as a contiguous run within `text_chars`, mirroring `str::find`'s
+/// "first match" semantics but in char space.
+fn find_char_subsequence(text_chars: &[char], pattern_chars: &[char]) -> Option<usize> {
+    if pattern_chars.is_empty() {
+        return Some(0);
+    }
+    if pattern_chars.len() > text_chars.len() {
+        return None;
+    }
+    (0..=(text_chars.len() - pattern_chars.len()))
+        .find(|&start| text_chars[start..start + pattern_chars.len()] == *pattern_chars)
+}
+
+fn is_boundary_char(c: char, unicode_aware: bool) -> bool {
+    if unicode_aware {
+        !c.is_alphanumeric()
+    } else {
+        !c.is_ascii_alphanumeric()
+    }
+}
+
 /// Checks if a pattern matches at word boundaries in the text.
 /// If unicode_aware is true, uses Unicode character classification.
 /// Otherwise, uses ASCII alphanumeric checks.
+///
+/// Runs an ASCII byte fast path when both `pattern` and `text` are pure
+/// ASCII (byte and char indices coincide there); otherwise falls back to
+/// a char-indexed search via [`Utf32`] so multibyte UTF-8 neither
+/// mis-locates the boundary nor indexes into the middle of a character.
 fn is_word_boundary_match(pattern: &str, text: &str, unicode_aware: bool) -> bool {
+    if pattern.is_ascii() && text.is_ascii() {
+        return is_word_boundary_match_ascii(pattern, text);
+    }
+
+    let text_u32 = Utf32::new(text);
+    let pattern_chars: Vec<char> = pattern.chars().collect();
+
+    let Some(pos) = find_char_subsequence(text_u32.chars(), &pattern_chars) else {
+        return false;
+    };
+
+    let before_ok = if pos == 0 {
+        true
+    } else {
+        is_boundary_char(text_u32.chars()[pos - 1], unicode_aware)
+    };
+
+    let after_idx = pos + pattern_chars.len();
+    let after_ok = if after_idx >= text_u32.len() {
+        true
+    } else {
+        is_boundary_char(text_u32.chars()[after_idx], unicode_aware)
+    };
+
+    before_ok && after_ok
+}
+
+fn is_word_boundary_match_ascii(pattern: &str, text: &str) -> bool {
     if let Some(pos) = text.find(pattern) {
-        let before_ok = if pos == 0 {
-            true
-        } else if unicode_aware {
-            !text[..pos]
-                .chars()
-                .last()
-                .map_or(false, |c| c.is_alphanumeric())
-        } else {
-            !text.as_bytes()[pos - 1].is_ascii_alphanumeric()
-        };
+        let before_ok = pos == 0 || !text.as_bytes()[pos - 1].is_ascii_alphanumeric();
 
         let after_pos = pos + pattern.len();
-        let after_ok = if after_pos >= text.len() {
-            true
-        } else if unicode_aware {
-            !text[after_pos..]
-                .chars()
-                .next()
-                .map_or(false, |c| c.is_alphanumeric())
-        } else {
-            !text.as_bytes()[after_pos].is_ascii_alphanumeric()
-        };
+        let after_ok = after_pos >= text.len() || !text.as_bytes()[after_pos].is_ascii_alphanumeric();
 
         before_ok && after_ok
     } else {
@@ -357,10 +860,76 @@ fn is_word_boundary_match(pattern: &str, text: &str, unicode_aware: bool) -> boo
     }
 }
 
+/// Folds a single Latin-1/Latin-Extended accented character to its base
+/// ASCII letter, preserving case (`É` -> `E`, `é` -> `e`), so pattern `cafe`
+/// can reach `café`. Characters outside the covered ranges are returned
+/// unchanged. Applied per character rather than as a bulk string copy, so
+/// callers can fold lazily while scanning a haystack.
+///
+/// `ß` is a special case: its correct fold is the two-character sequence
+/// `"ss"`, which this `char -> char` signature can't express. We fold it to
+/// `'s'` as the closest single-character approximation.
+pub(crate) fn normalize_char(c: char) -> char {
+    match c {
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => 'A',
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => 'E',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => 'I',
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => 'O',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => 'U',
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'Ý' | 'Ÿ' => 'Y',
+        'ý' | 'ÿ' => 'y',
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => 'N',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => 'C',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' => 'S',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'Ź' | 'Ż' | 'Ž' => 'Z',
+        'ź' | 'ż' | 'ž' => 'z',
+        'Ğ' | 'Ģ' | 'Ĝ' | 'Ġ' => 'G',
+        'ğ' | 'ģ' | 'ĝ' | 'ġ' => 'g',
+        'ß' => 's',
+        other => other,
+    }
+}
+
+/// Compares two characters for equality, ignoring ASCII case only, and — when
+/// `normalize` is set — after folding both through `normalize_char` first.
+fn chars_match(a: char, b: char, normalize: bool) -> bool {
+    if normalize {
+        normalize_char(a).eq_ignore_ascii_case(&normalize_char(b))
+    } else {
+        a.eq_ignore_ascii_case(&b)
+    }
+}
+
+/// Folds `s` through `normalize_char`, lowercasing unless `case_sensitive`.
+fn normalize_str(s: &str, case_sensitive: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        let folded = normalize_char(c);
+        if case_sensitive {
+            out.push(folded);
+        } else {
+            out.extend(folded.to_lowercase());
+        }
+    }
+    out
+}
+
 /// Checks if pattern is a substring of text.
-/// Respects case_sensitive_substring setting.
-fn is_substring_match(pattern: &str, text: &str, case_sensitive: bool) -> bool {
-    if case_sensitive {
+/// Respects case_sensitive_substring setting. When `normalize` is set,
+/// accented characters are folded to their ASCII base form first so e.g.
+/// `naive` reaches `naïve`.
+fn is_substring_match(pattern: &str, text: &str, case_sensitive: bool, normalize: bool) -> bool {
+    if normalize {
+        normalize_str(text, case_sensitive).contains(&normalize_str(pattern, case_sensitive))
+    } else if case_sensitive {
         text.contains(pattern)
     } else {
         text.to_lowercase().contains(&pattern.to_lowercase())
@@ -370,7 +939,10 @@ fn is_substring_match(pattern: &str, text: &str, case_sensitive: bool) -> bool {
 /// Counts consecutive character matches in text for the pattern.
 /// Returns (matched_count, is_all_consecutive).
 /// is_all_consecutive is true if all pattern characters appear without gaps in text.
-fn count_consecutive_matches(pattern: &str, text: &str) -> (usize, bool) {
+/// When `normalize` is set, accented characters are folded to their ASCII
+/// base form before comparison, one character at a time, so no normalized
+/// copy of `text` is allocated.
+fn count_consecutive_matches(pattern: &str, text: &str, normalize: bool) -> (usize, bool) {
     if pattern.is_empty() {
         return (0, true);
     }
@@ -383,7 +955,7 @@ fn count_consecutive_matches(pattern: &str, text: &str) -> (usize, bool) {
 
     for text_char in text.chars() {
         if let Some(&pattern_char) = pattern_chars.peek() {
-            if text_char.eq_ignore_ascii_case(&pattern_char) {
+            if chars_match(text_char, pattern_char, normalize) {
                 pattern_chars.next();
                 matched_count += 1;
                 last_gap_size = 0;
@@ -417,7 +989,10 @@ fn calculate_length_similarity(pattern: &str, text: &str) -> f32 {
 /// Performs fuzzy matching with configurable threshold.
 /// Returns true if at least threshold% of pattern characters appear in order in text.
 /// threshold should be between 0.0 and 1.0 (e.g., 0.6 = 60%).
-fn fuzzy_match_with_threshold(pattern: &str, text: &str, threshold: f32) -> bool {
+/// When `normalize` is set, accented characters are folded to their ASCII
+/// base form before comparison, one character at a time, so no normalized
+/// copy of `text` is allocated.
+fn fuzzy_match_with_threshold(pattern: &str, text: &str, threshold: f32, normalize: bool) -> bool {
     if pattern.is_empty() {
         return true;
     }
@@ -427,7 +1002,7 @@ fn fuzzy_match_with_threshold(pattern: &str, text: &str, threshold: f32) -> bool
 
     for text_char in text.chars() {
         if let Some(&pattern_char) = pattern_chars.peek() {
-            if text_char.eq_ignore_ascii_case(&pattern_char) {
+            if chars_match(text_char, pattern_char, normalize) {
                 pattern_chars.next();
                 matched_count += 1;
             }
@@ -443,14 +1018,281 @@ fn fuzzy_match_with_threshold(pattern: &str, text: &str, threshold: f32) -> bool
 /// Returns true if all pattern characters are found in order (case-insensitive).
 /// This is equivalent to fuzzy_match_with_threshold with threshold = 1.0.
 pub(crate) fn fuzzy_match(pattern: &str, text: &str) -> bool {
-    fuzzy_match_with_threshold(pattern, text, 1.0)
+    fuzzy_match_with_threshold(pattern, text, 1.0, false)
+}
+
+/// Base score for a single matched character in `optimal_fuzzy_score`.
+const OPTIMAL_MATCH_SCORE: i32 = 16;
+/// Per-character bonus, before scaling by `consecutive_match_bonus`, added
+/// for each step of an unbroken run. Unlike a flat consecutive bonus, this
+/// grows with the run length, so a long unbroken run is worth more than
+/// the same number of matches spread across several short runs.
+const OPTIMAL_RUN_BONUS_STEP: f32 = 4.0;
+/// Penalty for opening a gap -- the first text character skipped since the
+/// last match (or since the start of the text, for a leading gap).
+const OPTIMAL_GAP_OPEN_PENALTY: i32 = 3;
+/// Penalty for each additional skipped character once a gap is already
+/// open. Kept smaller than the open penalty so one long gap costs less
+/// than several short ones covering the same number of characters.
+const OPTIMAL_GAP_EXTEND_PENALTY: i32 = 1;
+
+/// broot-style cap on the number of holes (maximal gaps between matched
+/// characters) a fuzzy alignment may have before it's rejected outright as
+/// too scattered, scaling slowly with pattern length so short patterns
+/// demand a tight match while longer ones tolerate a few more gaps.
+fn max_holes_for_pattern_len(len: usize) -> usize {
+    match len {
+        0 | 1 => 0,
+        2 => 1,
+        3 => 2,
+        4 | 5 => 3,
+        6 => 4,
+        _ => 4 + (len - 6) / 4,
+    }
+}
+
+/// Counts, over the matched character positions of a fuzzy alignment
+/// (`char_positions[i]` is the text char index matched for pattern char
+/// `i`): the number of holes (maximal gaps between consecutive matched
+/// characters) and the number of isolated matches -- a matched character,
+/// neither first nor last, with an unmatched gap on both sides.
+fn count_holes_and_isolated(char_positions: &[usize]) -> (usize, usize) {
+    let holes = char_positions
+        .windows(2)
+        .filter(|w| w[1] - w[0] > 1)
+        .count();
+
+    let isolated = if char_positions.len() < 3 {
+        0
+    } else {
+        (1..char_positions.len() - 1)
+            .filter(|&i| {
+                char_positions[i] - char_positions[i - 1] > 1
+                    && char_positions[i + 1] - char_positions[i] > 1
+            })
+            .count()
+    };
+
+    (holes, isolated)
+}
+
+/// Cost of skipping `gap` text characters: free for no gap, otherwise the
+/// one-time open penalty plus an extend penalty for every character past
+/// the first.
+fn gap_penalty(gap: usize) -> i32 {
+    if gap == 0 {
+        0
+    } else {
+        OPTIMAL_GAP_OPEN_PENALTY + (gap as i32 - 1) * OPTIMAL_GAP_EXTEND_PENALTY
+    }
+}
+
+/// Bonus contributed by extending a consecutive run to `run_len`, scaled
+/// by `config.consecutive_match_bonus`. Grows with `run_len` so the later
+/// characters in a long run are worth progressively more, rewarding
+/// unbroken runs over scattered hits of the same total length.
+fn run_bonus(run_len: u32, config: &HeuristicConfig) -> i32 {
+    (run_len as f32 * OPTIMAL_RUN_BONUS_STEP * config.consecutive_match_bonus) as i32
+}
+
+/// Finds the best-scoring alignment of `pattern` as a (possibly
+/// non-contiguous) subsequence of `text`, via a Smith-Waterman-style
+/// dynamic program over `(pattern_index, text_index)` pairs, and returns
+/// the normalized score (0-1000) along with the byte offsets of the
+/// matched characters in `text`, for highlighting. Returns `None` if
+/// `pattern` isn't a subsequence of `text` at all.
+///
+/// `best[i][j]` is the highest score achievable by matching
+/// `pattern[..=i]` with the `i`-th character landing at `text[j]`, built
+/// from the best `best[i - 1][jp]` over every earlier position
+/// `jp < j`: extending it costs a gap penalty for the `j - jp - 1`
+/// skipped characters, or (when `jp == j - 1`, i.e. no gap at all) grants
+/// a run bonus that grows with how long the consecutive run ending here
+/// has become. `run_len` tracks that running length alongside `best` so
+/// the bonus calculation doesn't need to re-derive it from `backptr`.
+/// The best final alignment (by score, over every possible end position)
+/// is walked back through `backptr` to recover the matched positions.
+pub(crate) fn optimal_fuzzy_score(
+    pattern: &str,
+    text: &str,
+    config: &HeuristicConfig,
+) -> Option<(u32, Vec<usize>)> {
+    let pattern_chars: Vec<char> = pattern.chars().collect();
+    let text_chars: Vec<char> = text.chars().collect();
+
+    if pattern_chars.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    if text_chars.is_empty() {
+        return None;
+    }
+
+    let m = pattern_chars.len();
+    let n = text_chars.len();
+
+    let mut best: Vec<Vec<Option<i32>>> = vec![vec![None; n]; m];
+    let mut backptr: Vec<Vec<Option<usize>>> = vec![vec![None; n]; m];
+    let mut run_len: Vec<Vec<u32>> = vec![vec![1; n]; m];
+
+    for j in 0..n {
+        if !chars_match(pattern_chars[0], text_chars[j], config.normalize) {
+            continue;
+        }
+        best[0][j] = Some(OPTIMAL_MATCH_SCORE + run_bonus(1, config) - gap_penalty(j));
+    }
+
+    for i in 1..m {
+        for j in 0..n {
+            if !chars_match(pattern_chars[i], text_chars[j], config.normalize) {
+                continue;
+            }
+
+            let mut best_prev: Option<(i32, usize, u32)> = None;
+            for jp in 0..j {
+                let Some(prev_score) = best[i - 1][jp] else {
+                    continue;
+                };
+                let gap = j - jp - 1;
+                let new_run = if gap == 0 { run_len[i - 1][jp] + 1 } else { 1 };
+                let candidate_score =
+                    prev_score + OPTIMAL_MATCH_SCORE + run_bonus(new_run, config) - gap_penalty(gap);
+                if best_prev.map_or(true, |(s, _, _)| candidate_score > s) {
+                    best_prev = Some((candidate_score, jp, new_run));
+                }
+            }
+
+            if let Some((score, prev_j, new_run)) = best_prev {
+                best[i][j] = Some(score);
+                backptr[i][j] = Some(prev_j);
+                run_len[i][j] = new_run;
+            }
+        }
+    }
+
+    let last = m - 1;
+    let best_end = (0..n)
+        .filter_map(|j| best[last][j].map(|s| (s, j)))
+        .max_by_key(|&(s, _)| s);
+
+    let (raw_score, end_j) = best_end?;
+
+    let mut char_positions = vec![0usize; m];
+    let mut cur_j = end_j;
+    for i in (0..m).rev() {
+        char_positions[i] = cur_j;
+        if i > 0 {
+            cur_j = backptr[i][cur_j].expect("backtrack chain must exist for a matched alignment");
+        }
+    }
+
+    // Reject alignments that are too scattered outright, rather than just
+    // scoring them lower, so callers like `rank_candidates` can drop them.
+    let (holes, isolated) = count_holes_and_isolated(&char_positions);
+    let max_holes = config
+        .max_holes
+        .unwrap_or_else(|| max_holes_for_pattern_len(m));
+    if holes > max_holes {
+        return None;
+    }
+    let raw_score = raw_score
+        - (holes as f32 * config.hole_penalty) as i32
+        - (isolated as f32 * config.isolated_char_penalty) as i32;
+
+    let char_byte_offsets: Vec<usize> = text.char_indices().map(|(b, _)| b).collect();
+    let positions: Vec<usize> = char_positions
+        .iter()
+        .map(|&ci| char_byte_offsets[ci])
+        .collect();
+
+    // Normalize against a fully consecutive run of length m, the highest
+    // score an alignment of this pattern length could possibly achieve.
+    let max_possible: i32 = (1..=m as u32).map(|r| OPTIMAL_MATCH_SCORE + run_bonus(r, config)).sum();
+    let normalized = if max_possible > 0 {
+        ((raw_score.max(0) as f32 / max_possible as f32) * 1000.0).min(1000.0)
+    } else {
+        0.0
+    };
+
+    Some((normalized as u32, positions))
+}
+
+/// Computes a Selecta-style "tightest span" density score: scans
+/// `candidate` for the shortest window that contains every character of
+/// `query`, in order, as a subsequence (not necessarily contiguous) --
+/// trying every candidate position where `query`'s first character
+/// matches, then greedily advancing through the rest of `query` and
+/// keeping the shortest window found across all start positions.
+///
+/// The raw score is `query.len() / shortest_window_len`, then divided by
+/// `candidate.len()` so that among equally tight matches, shorter
+/// candidates score higher. Returns `1.0` for an empty `query`, or `0.0`
+/// when `query` isn't a subsequence of `candidate` at all.
+///
+/// This is an alternative to the additive `ScoringWeights` model in
+/// `calculate_relevance_score_with_config`, selected via
+/// `HeuristicConfig::span_scoring`; it rewards clustering ("tightness")
+/// rather than summing independent bonus factors.
+pub(crate) fn score_span(query: &str, candidate: &str) -> f64 {
+    if query.is_empty() {
+        return 1.0;
+    }
+
+    let query_chars: Vec<char> = query.chars().collect();
+    let candidate_chars: Vec<char> = candidate.chars().collect();
+
+    let mut shortest_window: Option<usize> = None;
+    for start in 0..candidate_chars.len() {
+        if candidate_chars[start] != query_chars[0] {
+            continue;
+        }
+
+        let mut query_idx = 1;
+        let mut end = start;
+        for (i, &c) in candidate_chars.iter().enumerate().skip(start + 1) {
+            if query_idx == query_chars.len() {
+                break;
+            }
+            if c == query_chars[query_idx] {
+                query_idx += 1;
+                end = i;
+            }
+        }
+
+        if query_idx == query_chars.len() {
+            let window_len = end - start + 1;
+            shortest_window = Some(shortest_window.map_or(window_len, |w| w.min(window_len)));
+        }
+    }
+
+    let Some(shortest_window) = shortest_window else {
+        return 0.0;
+    };
+
+    let tightness = query_chars.len() as f64 / shortest_window as f64;
+    tightness / candidate_chars.len() as f64
 }
 
-/// Calculates Levenshtein distance (edit distance) between two strings.
+/// Calculates Levenshtein distance (edit distance) between two strings, in
+/// characters rather than bytes.
 /// Lower distance = more similar. Useful for typo detection.
 ///
 /// Uses optimized O(min(len1, len2)) space complexity by keeping only two rows.
+///
+/// Runs an ASCII byte fast path when both strings are pure ASCII (a byte
+/// *is* a character there); otherwise collects both into `Vec<char>` first,
+/// since comparing raw UTF-8 bytes would split multibyte characters (CJK,
+/// accented, emoji) across DP cells and report a meaningless distance.
 pub(crate) fn levenshtein_distance(s1: &str, s2: &str) -> usize {
+    if s1.is_ascii() && s2.is_ascii() {
+        levenshtein_distance_elems(s1.as_bytes(), s2.as_bytes())
+    } else {
+        let c1: Vec<char> = s1.chars().collect();
+        let c2: Vec<char> = s2.chars().collect();
+        levenshtein_distance_elems(&c1, &c2)
+    }
+}
+
+fn levenshtein_distance_elems<T: PartialEq>(s1: &[T], s2: &[T]) -> usize {
     let len1 = s1.len();
     let len2 = s2.len();
 
@@ -468,9 +1310,6 @@ pub(crate) fn levenshtein_distance(s1: &str, s2: &str) -> usize {
         (s1, s2, len1, len2)
     };
 
-    let s1_bytes = s1.as_bytes();
-    let s2_bytes = s2.as_bytes();
-
     // Use two rows instead of full matrix
     let mut prev_row: Vec<usize> = (0..=len1).collect();
     let mut curr_row = vec![0; len1 + 1];
@@ -479,7 +1318,7 @@ pub(crate) fn levenshtein_distance(s1: &str, s2: &str) -> usize {
         curr_row[0] = j;
 
         for i in 1..=len1 {
-            let cost = if s1_bytes[i - 1] == s2_bytes[j - 1] { 0 } else { 1 };
+            let cost = if s1[i - 1] == s2[j - 1] { 0 } else { 1 };
             curr_row[i] = std::cmp::min(
                 std::cmp::min(
                     prev_row[i] + 1,           // deletion
@@ -495,71 +1334,454 @@ pub(crate) fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     prev_row[len1]
 }
 
-/// Checks if text is a close match to pattern (within edit distance threshold).
-/// If max_distance is None, uses pattern.len() / 4 as the threshold.
-pub(crate) fn is_close_match(pattern: &str, text: &str, max_distance: Option<usize>) -> bool {
-    let threshold = max_distance.unwrap_or_else(|| std::cmp::max(1, pattern.len() / 4));
-    levenshtein_distance(pattern, text) <= threshold
+/// Calculates the Damerau-Levenshtein distance (optimal string alignment
+/// variant) between two strings, in characters rather than bytes. Like
+/// `levenshtein_distance`, but also counts a single adjacent-character
+/// swap (e.g. "teh" -> "the") as one edit instead of the two substitutions
+/// a plain Levenshtein distance would charge for it -- catching the most
+/// common keyboard typo.
+///
+/// Uses the optimal-string-alignment recurrence: the same two-row
+/// Levenshtein DP, plus a transposition case checked against the row from
+/// two steps back, so three rows are kept instead of two. Unlike true
+/// Damerau-Levenshtein, OSA doesn't allow a transposed pair to be edited
+/// again afterwards, which is an accepted trade-off for the simpler
+/// recurrence.
+///
+/// Runs an ASCII byte fast path when both strings are pure ASCII;
+/// otherwise collects both into `Vec<char>` first, for the same
+/// multibyte-correctness reason as `levenshtein_distance`.
+pub(crate) fn damerau_levenshtein_distance(s1: &str, s2: &str) -> usize {
+    if s1.is_ascii() && s2.is_ascii() {
+        damerau_levenshtein_distance_elems(s1.as_bytes(), s2.as_bytes())
+    } else {
+        let c1: Vec<char> = s1.chars().collect();
+        let c2: Vec<char> = s2.chars().collect();
+        damerau_levenshtein_distance_elems(&c1, &c2)
+    }
 }
 
-/// Ranks multiple candidates by relevance to a pattern.
-/// Returns candidates sorted by score (highest first).
-pub(crate) fn rank_candidates(
-    pattern: &str,
-    candidates: &[&str],
-    config: &HeuristicConfig,
-) -> Vec<ScoredMatch> {
-    let mut scored: Vec<ScoredMatch> = candidates
-        .iter()
-        .map(|&text| {
-            let score = calculate_relevance_score_with_config(
-                pattern,
-                text,
-                pattern == text,
-                false,
-                config,
-            );
-            ScoredMatch {
-                text: text.to_string(),
-                score,
-            }
-        })
-        .filter(|m| m.score > 0) // Filter out non-matches
-        .collect();
+fn damerau_levenshtein_distance_elems<T: PartialEq>(s1: &[T], s2: &[T]) -> usize {
+    let len1 = s1.len();
+    let len2 = s2.len();
 
-    scored.sort();
-    scored
-}
+    if len1 == 0 {
+        return len2;
+    }
+    if len2 == 0 {
+        return len1;
+    }
 
-/// Finds matching character positions in text for a pattern.
-/// Returns a vector of (start, end) byte positions of matches.
-/// Useful for highlighting matched portions in UI.
-pub(crate) fn find_match_positions(pattern: &str, text: &str) -> Vec<(usize, usize)> {
-    let mut positions = Vec::new();
-    let pattern_lower = pattern.to_lowercase();
-    let text_lower = text.to_lowercase();
+    // Optimize by using the shorter string as s1
+    let (s1, s2, len1, len2) = if len1 > len2 {
+        (s2, s1, len2, len1)
+    } else {
+        (s1, s2, len1, len2)
+    };
 
-    // Find all occurrences of pattern as substring
-    let mut start = 0;
-    while let Some(pos) = text_lower[start..].find(&pattern_lower) {
-        let absolute_pos = start + pos;
-        positions.push((absolute_pos, absolute_pos + pattern.len()));
-        start = absolute_pos + 1;
-    }
+    // Three rows instead of two: `prev2_row` (two steps back) is needed to
+    // detect transpositions.
+    let mut prev2_row: Vec<usize> = vec![0; len1 + 1];
+    let mut prev_row: Vec<usize> = (0..=len1).collect();
+    let mut curr_row = vec![0; len1 + 1];
 
-    positions
-}
+    for j in 1..=len2 {
+        curr_row[0] = j;
 
-/// Highlights matching portions of text with markers.
-/// Useful for displaying search results with highlighted matches.
-/// Markers are placed around matched portions: prefix + match + suffix
-pub(crate) fn highlight_matches(
-    pattern: &str,
-    text: &str,
+        for i in 1..=len1 {
+            let cost = if s1[i - 1] == s2[j - 1] { 0 } else { 1 };
+            let mut best = std::cmp::min(
+                std::cmp::min(
+                    prev_row[i] + 1,       // deletion
+                    curr_row[i - 1] + 1,   // insertion
+                ),
+                prev_row[i - 1] + cost,    // substitution
+            );
+
+            if i > 1 && j > 1 && s1[i - 1] == s2[j - 2] && s1[i - 2] == s2[j - 1] {
+                best = std::cmp::min(best, prev2_row[i - 2] + 1);
+            }
+
+            curr_row[i] = best;
+        }
+
+        std::mem::swap(&mut prev_row, &mut curr_row);
+        std::mem::swap(&mut prev2_row, &mut curr_row);
+    }
+
+    prev_row[len1]
+}
+
+/// Checks if text is a close match to pattern (within edit distance threshold).
+/// If max_distance is None, uses pattern.chars().count() / 4 as the threshold.
+///
+/// Uses `damerau_levenshtein_distance` when `allow_transpositions` is true
+/// (matching `HeuristicConfig::allow_transpositions`'s default), so a
+/// single adjacent-character swap counts as distance 1 instead of 2.
+pub(crate) fn is_close_match(
+    pattern: &str,
+    text: &str,
+    max_distance: Option<usize>,
+    allow_transpositions: bool,
+) -> bool {
+    let threshold =
+        max_distance.unwrap_or_else(|| std::cmp::max(1, pattern.chars().count() / 4));
+    let distance = if allow_transpositions {
+        damerau_levenshtein_distance(pattern, text)
+    } else {
+        levenshtein_distance(pattern, text)
+    };
+    distance <= threshold
+}
+
+/// Cheap necessary-condition prefilter for [`rank_candidates`], modeled on
+/// fzf's `ascii_fuzzy_index`: scans the case-folded haystack once,
+/// verifying every pattern byte occurs in `text` in order, and returns the
+/// byte offset of the first occurrence of the pattern's first character --
+/// the earliest feasible start a subsequence match could have. Returns
+/// `None` when the pattern can't possibly match as a subsequence, letting
+/// callers drop the candidate before paying for any float scoring.
+///
+/// Only optimizes pure-ASCII `pattern`/`text`, mirroring fzf: a byte-level
+/// scan can't fold Unicode case or accents correctly, so non-ASCII input
+/// always survives and leaves the real work to the full scorer.
+pub(crate) fn prefilter(pattern: &str, text: &str) -> Option<usize> {
+    if pattern.is_empty() || !pattern.is_ascii() || !text.is_ascii() {
+        return Some(0);
+    }
+
+    let pattern_bytes = pattern.as_bytes();
+    let mut pat_idx = 0;
+    let mut first_start = None;
+
+    for (i, &b) in text.as_bytes().iter().enumerate() {
+        if pat_idx == pattern_bytes.len() {
+            break;
+        }
+        if b.to_ascii_lowercase() == pattern_bytes[pat_idx].to_ascii_lowercase() {
+            if first_start.is_none() {
+                first_start = Some(i);
+            }
+            pat_idx += 1;
+        }
+    }
+
+    if pat_idx == pattern_bytes.len() {
+        first_start
+    } else {
+        None
+    }
+}
+
+/// Splits `text` into word tokens on whitespace and `delimiters`,
+/// dropping empty tokens from runs of adjacent separators. Shared by
+/// `word_position_sum` for tokenizing both the query and the candidate.
+fn tokenize_words<'a>(text: &'a str, delimiters: &[char]) -> Vec<&'a str> {
+    text.split(|c: char| c.is_whitespace() || delimiters.contains(&c))
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// MeiliSearch-style `SumOfWordsPosition` criterion: tokenizes `pattern`
+/// into query terms and `text` into candidate words (both on whitespace
+/// and `delimiters`), and for each query term found as a case-insensitive
+/// substring of some candidate word, adds that word's index to the sum --
+/// counting only the term's first (earliest) matching word, so a term
+/// that recurs later in `text` doesn't inflate the sum. Lower sums mean
+/// the query terms landed earlier in `text`. Returns `None` if no query
+/// term matched any candidate word at all.
+pub(crate) fn word_position_sum(pattern: &str, text: &str, delimiters: &[char]) -> Option<usize> {
+    let query_terms = tokenize_words(pattern, delimiters);
+    let candidate_words = tokenize_words(text, delimiters);
+
+    let mut sum = 0usize;
+    let mut matched_any = false;
+    for term in &query_terms {
+        let term_lower = term.to_lowercase();
+        if let Some(idx) = candidate_words
+            .iter()
+            .position(|w| w.to_lowercase().contains(&term_lower))
+        {
+            sum += idx;
+            matched_any = true;
+        }
+    }
+
+    matched_any.then_some(sum)
+}
+
+/// Returns the `(start_char_idx, end_char_idx)` span of every word token
+/// in `text`, tokenized the same way as `tokenize_words`, in order -- so
+/// a token's position in this list is its word index.
+fn word_token_spans(text: &str, delimiters: &[char]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut token_start: Option<usize> = None;
+    let mut char_idx = 0usize;
+
+    for c in text.chars() {
+        if c.is_whitespace() || delimiters.contains(&c) {
+            if let Some(start) = token_start.take() {
+                spans.push((start, char_idx));
+            }
+        } else if token_start.is_none() {
+            token_start = Some(char_idx);
+        }
+        char_idx += 1;
+    }
+    if let Some(start) = token_start {
+        spans.push((start, char_idx));
+    }
+
+    spans
+}
+
+/// Finds the index of the token span in `spans` (as produced by
+/// `word_token_spans`) containing char index `idx`.
+fn token_index_for_char(spans: &[(usize, usize)], idx: usize) -> Option<usize> {
+    spans.iter().position(|&(start, end)| idx >= start && idx < end)
+}
+
+/// Recursively tries every assignment of one occurrence per query word
+/// (`candidate_positions[i]` is the list of word indices where query word
+/// `i` occurs in `text`), tracking the minimal sum of absolute word-index
+/// gaps between adjacent query words across all assignments.
+fn minimal_phrase_gap(candidate_positions: &[Vec<usize>]) -> usize {
+    fn search(depth: usize, assignment: &mut Vec<usize>, candidate_positions: &[Vec<usize>]) -> usize {
+        if depth == candidate_positions.len() {
+            return assignment
+                .windows(2)
+                .map(|w| w[1].abs_diff(w[0]))
+                .sum();
+        }
+        candidate_positions[depth]
+            .iter()
+            .map(|&pos| {
+                assignment.push(pos);
+                let total = search(depth + 1, assignment, candidate_positions);
+                assignment.pop();
+                total
+            })
+            .min()
+            .unwrap_or(usize::MAX)
+    }
+
+    let mut assignment = Vec::with_capacity(candidate_positions.len());
+    search(0, &mut assignment, candidate_positions)
+}
+
+/// Tantivy-style bidirectional-slop phrase proximity: tokenizes `pattern`
+/// into query words, locates each word's occurrences in `text` via
+/// `find_match_positions` (mapped to the word-token index of `text` each
+/// occurrence falls within), and finds the assignment of one occurrence
+/// per query word that minimizes the sum of absolute word-index gaps
+/// between *adjacent query words*. Unlike a forward-only phrase match, a
+/// later query word may land before an earlier one in `text`, since gaps
+/// are taken as absolute values rather than requiring ascending order.
+///
+/// Returns `None` when `pattern` has fewer than two words (there's no
+/// adjacent pair to measure), when any query word has no occurrence in
+/// `text` at all, or when the minimal total gap exceeds `slop`.
+pub(crate) fn phrase_proximity(
+    pattern: &str,
+    text: &str,
+    delimiters: &[char],
+    normalize: bool,
+    slop: usize,
+) -> Option<usize> {
+    let query_words = tokenize_words(pattern, delimiters);
+    if query_words.len() < 2 {
+        return None;
+    }
+
+    let token_spans = word_token_spans(text, delimiters);
+
+    let mut candidate_positions: Vec<Vec<usize>> = Vec::with_capacity(query_words.len());
+    for word in &query_words {
+        let mut indices: Vec<usize> = find_match_positions(word, text, normalize)
+            .iter()
+            .filter_map(|&(start_byte, _)| {
+                let start_char = text[..start_byte].chars().count();
+                token_index_for_char(&token_spans, start_char)
+            })
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices.is_empty() {
+            return None;
+        }
+        candidate_positions.push(indices);
+    }
+
+    let gap = minimal_phrase_gap(&candidate_positions);
+    (gap <= slop).then_some(gap)
+}
+
+/// Ranks multiple candidates by relevance to a pattern.
+/// Returns candidates sorted by score (highest first).
+pub(crate) fn rank_candidates(
+    pattern: &str,
+    candidates: &[&str],
+    config: &HeuristicConfig,
+) -> Vec<ScoredMatch> {
+    let mut scored: Vec<ScoredMatch> = candidates
+        .iter()
+        .filter_map(|&text| {
+            prefilter(pattern, text)?;
+            let fuzzy = optimal_fuzzy_score(pattern, text, config);
+            // `None` means either no subsequence match at all, or (for a
+            // non-empty pattern) one too scattered to stay under
+            // `max_holes` -- drop it rather than scoring it on other
+            // bonuses alone.
+            if !pattern.is_empty() && fuzzy.is_none() {
+                return None;
+            }
+            let score = calculate_relevance_score_with_config(
+                pattern,
+                text,
+                pattern == text,
+                false,
+                config,
+            );
+            let positions = fuzzy.map(|(_, positions)| positions).unwrap_or_default();
+            let word_position_sum = config
+                .word_position_tiebreak
+                .then(|| word_position_sum(pattern, text, &config.delimiters))
+                .flatten();
+            Some(ScoredMatch {
+                text: text.to_string(),
+                score,
+                positions,
+                word_position_sum,
+            })
+        })
+        .filter(|m| m.score > 0) // Filter out non-matches
+        .collect();
+
+    scored.sort();
+    scored
+}
+
+/// Like [`rank_candidates`], but pairs each result with a [`ScoreDetails`]
+/// breakdown of how its score was composed, so callers can debug weight
+/// tuning or implement secondary sort keys without reimplementing the
+/// scorer. Results are sorted by `ScoredMatch`'s existing `Ord` (final
+/// score, highest first), matching `rank_candidates`.
+pub(crate) fn rank_candidates_detailed(
+    pattern: &str,
+    candidates: &[&str],
+    config: &HeuristicConfig,
+) -> Vec<(ScoredMatch, ScoreDetails)> {
+    let mut scored: Vec<(ScoredMatch, ScoreDetails)> = candidates
+        .iter()
+        .filter_map(|&text| {
+            prefilter(pattern, text)?;
+            let fuzzy = optimal_fuzzy_score(pattern, text, config);
+            if !pattern.is_empty() && fuzzy.is_none() {
+                return None;
+            }
+            let is_exact = pattern == text;
+            let score =
+                calculate_relevance_score_with_config(pattern, text, is_exact, false, config);
+            let details = calculate_relevance_score_details(pattern, text, is_exact, false, config);
+            let positions = fuzzy.map(|(_, positions)| positions).unwrap_or_default();
+            let word_position_sum = config
+                .word_position_tiebreak
+                .then(|| word_position_sum(pattern, text, &config.delimiters))
+                .flatten();
+            Some((
+                ScoredMatch {
+                    text: text.to_string(),
+                    score,
+                    positions,
+                    word_position_sum,
+                },
+                details,
+            ))
+        })
+        .filter(|(m, _)| m.score > 0)
+        .collect();
+
+    scored.sort_by(|a, b| a.0.cmp(&b.0));
+    scored
+}
+
+/// Finds matching character positions in text for a pattern.
+/// Returns a vector of (start, end) byte positions of matches.
+/// Useful for highlighting matched portions in UI.
+///
+/// When `normalize` is set, accented characters are folded to their ASCII
+/// base form before comparison (so pattern `cafe` finds `café`), but the
+/// returned positions always index into the original, unnormalized `text`
+/// so callers like `highlight_matches` highlight the accented source.
+///
+/// Runs an ASCII byte fast path when `normalize` is off and both `pattern`
+/// and `text` are pure ASCII; otherwise matches in char space via
+/// [`Utf32`]. The char-space path is required even without `normalize`,
+/// since `str::to_lowercase` can change a character's UTF-8 byte length
+/// (e.g. `İ` -> `"i̇"`), which would otherwise desync byte offsets found in
+/// a lowercased copy from offsets in the original `text`.
+pub(crate) fn find_match_positions(
+    pattern: &str,
+    text: &str,
+    normalize: bool,
+) -> Vec<(usize, usize)> {
+    if !normalize && pattern.is_ascii() && text.is_ascii() {
+        return find_match_positions_ascii(pattern, text);
+    }
+
+    let fold = |c: char| -> char {
+        let base = if normalize { normalize_char(c) } else { c };
+        base.to_lowercase().next().unwrap_or(base)
+    };
+
+    let pattern_folded: Vec<char> = pattern.chars().map(fold).collect();
+    if pattern_folded.is_empty() {
+        return Vec::new();
+    }
+
+    let text_u32 = Utf32::new(text);
+    let folded_text: Vec<char> = text_u32.chars().iter().copied().map(fold).collect();
+
+    let mut positions = Vec::new();
+    if pattern_folded.len() > folded_text.len() {
+        return positions;
+    }
+    for start in 0..=(folded_text.len() - pattern_folded.len()) {
+        let end = start + pattern_folded.len();
+        if folded_text[start..end] == pattern_folded[..] {
+            positions.push(text_u32.byte_range(start, end));
+        }
+    }
+
+    positions
+}
+
+fn find_match_positions_ascii(pattern: &str, text: &str) -> Vec<(usize, usize)> {
+    let mut positions = Vec::new();
+    let pattern_lower = pattern.to_ascii_lowercase();
+    let text_lower = text.to_ascii_lowercase();
+
+    // Find all occurrences of pattern as substring
+    let mut start = 0;
+    while let Some(pos) = text_lower[start..].find(&pattern_lower) {
+        let absolute_pos = start + pos;
+        positions.push((absolute_pos, absolute_pos + pattern.len()));
+        start = absolute_pos + 1;
+    }
+
+    positions
+}
+
+/// Highlights matching portions of text with markers.
+/// Useful for displaying search results with highlighted matches.
+/// Markers are placed around matched portions: prefix + match + suffix
+pub(crate) fn highlight_matches(
+    pattern: &str,
+    text: &str,
     prefix: &str,
     suffix: &str,
 ) -> String {
-    let positions = find_match_positions(pattern, text);
+    let positions = find_match_positions(pattern, text, false);
 
     if positions.is_empty() {
         return text.to_string();
@@ -583,6 +1805,295 @@ pub(crate) fn highlight_matches(
     result
 }
 
+/// Base score for a single matched character, before bonuses/penalties.
+const FUZZY_SCORE_MATCH: i32 = 16;
+/// Bonus for a character match landing right after a path separator,
+/// underscore, hyphen, dot, or a lower→upper camelCase transition.
+const FUZZY_BONUS_BOUNDARY: i32 = 8;
+/// Bonus for a character match immediately following the previous match,
+/// i.e. no gap between them.
+const FUZZY_BONUS_CONSECUTIVE: i32 = 4;
+/// Penalty per skipped character, applied to both the leading gap (before
+/// the first match) and every inter-match gap.
+const FUZZY_PENALTY_GAP: i32 = 1;
+
+/// Result of a fuzzy subsequence match against a single candidate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FuzzyMatch {
+    /// Whether `query` matched as a subsequence of the candidate at all.
+    pub matched: bool,
+    /// Score normalized into the 0-1000 range used by `CachedResult::score`.
+    pub score: u32,
+    /// Byte offsets of the matched characters in the candidate, in order,
+    /// for highlighting the match in a UI.
+    pub positions: Vec<usize>,
+}
+
+/// fzf-style fuzzy subsequence matcher with positional scoring.
+/// Matches a query as a (possibly non-contiguous) subsequence of a
+/// candidate string and scores the best alignment, favoring matches at word
+/// boundaries and consecutive runs while penalizing gaps between matches.
+pub(crate) struct FuzzyMatcher;
+
+impl FuzzyMatcher {
+    /// Creates a new fuzzy matcher.
+    pub(crate) fn new() -> Self {
+        FuzzyMatcher
+    }
+
+    /// Checks whether `chars[idx]` starts a "word" for bonus purposes: the
+    /// very first character, one after a path/identifier separator, or a
+    /// camelCase transition from a lowercase to an uppercase letter.
+    fn is_word_boundary(chars: &[char], idx: usize) -> bool {
+        if idx == 0 {
+            return true;
+        }
+        let prev = chars[idx - 1];
+        if matches!(prev, '/' | '_' | '-' | '.') {
+            return true;
+        }
+        prev.is_lowercase() && chars[idx].is_uppercase()
+    }
+
+    /// Matches `query` as a subsequence of `candidate`, returning whether it
+    /// matched, its score normalized to 0-1000, and the matched byte
+    /// offsets in `candidate` for highlighting.
+    ///
+    /// Runs a dynamic-programming pass over `(query_index, candidate_index)`
+    /// pairs: `best[i][j]` is the highest score achievable by matching the
+    /// first `i + 1` query characters with the `i`-th one landing at
+    /// `candidate[j]`, extending `best[i - 1][j']` for every earlier
+    /// position `j' < j` with a match-score, boundary bonus, and a
+    /// consecutive bonus or gap penalty depending on `j - j' - 1`. The best
+    /// final alignment is walked back through `backptr` to recover
+    /// positions.
+    pub(crate) fn score_match(&self, query: &str, candidate: &str) -> FuzzyMatch {
+        let query_chars: Vec<char> = query.chars().collect();
+        let candidate_chars: Vec<char> = candidate.chars().collect();
+
+        if query_chars.is_empty() {
+            return FuzzyMatch {
+                matched: true,
+                score: 0,
+                positions: Vec::new(),
+            };
+        }
+        if candidate_chars.is_empty() {
+            return FuzzyMatch {
+                matched: false,
+                score: 0,
+                positions: Vec::new(),
+            };
+        }
+
+        let m = query_chars.len();
+        let n = candidate_chars.len();
+
+        // best[i][j]: best score matching query[..=i] with query[i] landing
+        // at candidate[j]. backptr[i][j]: the candidate index query[i - 1]
+        // landed at, for backtracking the alignment.
+        let mut best: Vec<Vec<Option<i32>>> = vec![vec![None; n]; m];
+        let mut backptr: Vec<Vec<Option<usize>>> = vec![vec![None; n]; m];
+
+        for j in 0..n {
+            if !query_chars[0].eq_ignore_ascii_case(&candidate_chars[j]) {
+                continue;
+            }
+            let boundary_bonus = if Self::is_word_boundary(&candidate_chars, j) {
+                FUZZY_BONUS_BOUNDARY
+            } else {
+                0
+            };
+            let leading_gap_penalty = j as i32 * FUZZY_PENALTY_GAP;
+            best[0][j] = Some(FUZZY_SCORE_MATCH + boundary_bonus - leading_gap_penalty);
+        }
+
+        for i in 1..m {
+            for j in 0..n {
+                if !query_chars[i].eq_ignore_ascii_case(&candidate_chars[j]) {
+                    continue;
+                }
+                let boundary_bonus = if Self::is_word_boundary(&candidate_chars, j) {
+                    FUZZY_BONUS_BOUNDARY
+                } else {
+                    0
+                };
+
+                let mut best_prev: Option<(i32, usize)> = None;
+                for jp in 0..j {
+                    let Some(prev_score) = best[i - 1][jp] else {
+                        continue;
+                    };
+                    let gap = j - jp - 1;
+                    let transition = if gap == 0 {
+                        FUZZY_BONUS_CONSECUTIVE
+                    } else {
+                        -(gap as i32 * FUZZY_PENALTY_GAP)
+                    };
+                    let candidate_score = prev_score + FUZZY_SCORE_MATCH + boundary_bonus + transition;
+                    if best_prev.map_or(true, |(s, _)| candidate_score > s) {
+                        best_prev = Some((candidate_score, jp));
+                    }
+                }
+
+                if let Some((score, prev_j)) = best_prev {
+                    best[i][j] = Some(score);
+                    backptr[i][j] = Some(prev_j);
+                }
+            }
+        }
+
+        let last = m - 1;
+        let best_end = (0..n)
+            .filter_map(|j| best[last][j].map(|s| (s, j)))
+            .max_by_key(|&(s, _)| s);
+
+        let Some((raw_score, end_j)) = best_end else {
+            return FuzzyMatch {
+                matched: false,
+                score: 0,
+                positions: Vec::new(),
+            };
+        };
+
+        let mut char_positions = vec![0usize; m];
+        let mut cur_j = end_j;
+        for i in (0..m).rev() {
+            char_positions[i] = cur_j;
+            if i > 0 {
+                cur_j = backptr[i][cur_j].expect("backtrack chain must exist for a matched alignment");
+            }
+        }
+
+        let char_byte_offsets: Vec<usize> = candidate.char_indices().map(|(b, _)| b).collect();
+        let positions: Vec<usize> = char_positions
+            .iter()
+            .map(|&ci| char_byte_offsets[ci])
+            .collect();
+
+        // Normalize against the maximum score a perfect, all-boundary,
+        // fully-consecutive match of this length could achieve.
+        let max_possible =
+            m as i32 * (FUZZY_SCORE_MATCH + FUZZY_BONUS_BOUNDARY + FUZZY_BONUS_CONSECUTIVE);
+        let normalized = if max_possible > 0 {
+            ((raw_score.max(0) as f32 / max_possible as f32) * 1000.0).min(1000.0)
+        } else {
+            0.0
+        };
+
+        FuzzyMatch {
+            matched: true,
+            score: normalized as u32,
+            positions,
+        }
+    }
+}
+
+impl Default for FuzzyMatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Base score for a single matched character in `positional_bonus_score`.
+const POSITIONAL_MATCH_SCORE: f32 = 16.0;
+
+/// Performs a greedy left-to-right fzf v1-style scan: unlike
+/// `optimal_fuzzy_score`'s dynamic program, which searches every possible
+/// alignment for the global optimum, this walks `pattern` once against
+/// `text`, matching each character at the first available position at or
+/// after the previous match. Cheaper than the DP approach, at the cost of
+/// occasionally missing the true best alignment when an earlier greedy
+/// pick forecloses a better one later on.
+///
+/// Per matched character, accumulates: a flat match score; a boundary
+/// bonus (`config.fzf_boundary_bonus`) when the character is the first in
+/// `text`, follows a delimiter or whitespace, or sits at a camelCase
+/// transition; an extra one-time bonus (`config.fzf_first_char_bonus`)
+/// for the very first matched pattern character; a consecutive-run bonus
+/// (`config.fzf_consecutive_bonus`) when it immediately follows the
+/// previous match with no gap; a gap-start penalty
+/// (`config.fzf_gap_start_penalty`) the first time a run breaks, plus a
+/// smaller gap-extension penalty (`config.fzf_gap_extension_penalty`)
+/// for each further skipped character; and a case-mismatch penalty
+/// (`config.fzf_case_mismatch_penalty`) when a lowercase pattern
+/// character matches an uppercase text character. Returns `None` if
+/// `pattern` isn't a subsequence of `text` at all, or `Some((0, vec![]))`
+/// for an empty pattern.
+pub(crate) fn positional_bonus_score(
+    pattern: &str,
+    text: &str,
+    config: &HeuristicConfig,
+) -> Option<(u32, Vec<usize>)> {
+    let pattern_chars: Vec<char> = pattern.chars().collect();
+    let text_chars: Vec<char> = text.chars().collect();
+
+    if pattern_chars.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    if text_chars.is_empty() {
+        return None;
+    }
+
+    let mut char_positions = Vec::with_capacity(pattern_chars.len());
+    let mut score = 0.0f32;
+    let mut search_from = 0usize;
+
+    for (pi, &pc) in pattern_chars.iter().enumerate() {
+        let match_idx =
+            (search_from..text_chars.len()).find(|&j| chars_match(pc, text_chars[j], config.normalize))?;
+
+        let gap = match_idx - search_from;
+        if gap > 0 {
+            score -=
+                config.fzf_gap_start_penalty + (gap - 1) as f32 * config.fzf_gap_extension_penalty;
+        } else if pi > 0 {
+            score += config.fzf_consecutive_bonus;
+        }
+
+        score += POSITIONAL_MATCH_SCORE;
+
+        if pi == 0 {
+            score += config.fzf_first_char_bonus;
+        }
+
+        let is_boundary = match_idx == 0 || {
+            let prev_class = classify_char(text_chars[match_idx - 1], &config.delimiters);
+            matches!(prev_class, CharClass::Delimiter | CharClass::Whitespace | CharClass::NonWord)
+                || (prev_class == CharClass::Lower && text_chars[match_idx].is_uppercase())
+        };
+        if is_boundary {
+            score += config.fzf_boundary_bonus;
+        }
+
+        if pc.is_lowercase() && text_chars[match_idx].is_uppercase() {
+            score -= config.fzf_case_mismatch_penalty;
+        }
+
+        char_positions.push(match_idx);
+        search_from = match_idx + 1;
+    }
+
+    let char_byte_offsets: Vec<usize> = text.char_indices().map(|(b, _)| b).collect();
+    let positions: Vec<usize> = char_positions
+        .iter()
+        .map(|&ci| char_byte_offsets[ci])
+        .collect();
+
+    // Normalize against the maximum score a perfect, all-boundary,
+    // fully-consecutive match of this length could achieve.
+    let max_possible = pattern_chars.len() as f32
+        * (POSITIONAL_MATCH_SCORE + config.fzf_boundary_bonus + config.fzf_consecutive_bonus)
+        + config.fzf_first_char_bonus;
+    let normalized = if max_possible > 0.0 {
+        ((score.max(0.0) / max_possible) * 1000.0).min(1000.0)
+    } else {
+        0.0
+    };
+
+    Some((normalized as u32, positions))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -598,9 +2109,109 @@ mod tests {
 
     #[test]
     fn test_fuzzy_match_with_threshold() {
-        assert!(fuzzy_match_with_threshold("fn", "function", 0.5));
-        assert!(fuzzy_match_with_threshold("abc", "aXbXc", 0.6));
-        assert!(!fuzzy_match_with_threshold("xyz", "abc", 0.5));
+        assert!(fuzzy_match_with_threshold("fn", "function", 0.5, false));
+        assert!(fuzzy_match_with_threshold("abc", "aXbXc", 0.6, false));
+        assert!(!fuzzy_match_with_threshold("xyz", "abc", 0.5, false));
+    }
+
+    #[test]
+    fn test_fuzzy_match_with_threshold_normalize() {
+        assert!(!fuzzy_match_with_threshold("cafe", "café", 1.0, false));
+        assert!(fuzzy_match_with_threshold("cafe", "café", 1.0, true));
+    }
+
+    #[test]
+    fn test_optimal_fuzzy_score_no_match_returns_none() {
+        let config = HeuristicConfig::default();
+        assert_eq!(optimal_fuzzy_score("xyz", "abc", &config), None);
+    }
+
+    #[test]
+    fn test_optimal_fuzzy_score_empty_pattern_matches_trivially() {
+        let config = HeuristicConfig::default();
+        assert_eq!(
+            optimal_fuzzy_score("", "anything", &config),
+            Some((0, Vec::new()))
+        );
+    }
+
+    #[test]
+    fn test_optimal_fuzzy_score_consecutive_run_beats_scattered() {
+        let config = HeuristicConfig::default();
+        let (consecutive, _) = optimal_fuzzy_score("abc", "abcx", &config).unwrap();
+        let (scattered, _) = optimal_fuzzy_score("abc", "axbxc", &config).unwrap();
+        assert!(consecutive > scattered);
+    }
+
+    #[test]
+    fn test_optimal_fuzzy_score_returns_match_positions() {
+        let config = HeuristicConfig::default();
+        let (_, positions) = optimal_fuzzy_score("abc", "aXbXc", &config).unwrap();
+        assert_eq!(positions, vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_optimal_fuzzy_score_front_anchored_consecutive_scores_max() {
+        let config = HeuristicConfig::default();
+        let (score, _) = optimal_fuzzy_score("fn", "fn", &config).unwrap();
+        assert_eq!(score, 1000);
+    }
+
+    #[test]
+    fn test_max_holes_for_pattern_len_scales_slowly() {
+        assert_eq!(max_holes_for_pattern_len(1), 0);
+        assert_eq!(max_holes_for_pattern_len(2), 1);
+        assert_eq!(max_holes_for_pattern_len(3), 2);
+        assert_eq!(max_holes_for_pattern_len(4), 3);
+        assert_eq!(max_holes_for_pattern_len(5), 3);
+        assert_eq!(max_holes_for_pattern_len(6), 4);
+    }
+
+    #[test]
+    fn test_optimal_fuzzy_score_rejects_overly_scattered_match() {
+        let config = HeuristicConfig::default();
+        // "abcde" has max_holes = 3, but every one of its 4 inter-match
+        // gaps is a hole here. Rather than a low score, this must be
+        // rejected outright.
+        assert_eq!(optimal_fuzzy_score("abcde", "a.b.c.d.e", &config), None);
+    }
+
+    #[test]
+    fn test_optimal_fuzzy_score_max_holes_override_allows_scattered_match() {
+        let mut config = HeuristicConfig::default();
+        config.max_holes = Some(usize::MAX);
+        assert!(optimal_fuzzy_score("abcde", "a.b.c.d.e", &config).is_some());
+    }
+
+    #[test]
+    fn test_count_holes_and_isolated() {
+        // "a_b_c": b at index 2 has gaps on both sides and isn't first/last.
+        assert_eq!(count_holes_and_isolated(&[0, 2, 4]), (2, 1));
+        // No gaps at all: no holes, no isolated matches.
+        assert_eq!(count_holes_and_isolated(&[0, 1, 2]), (0, 0));
+        // Single gap at the edge only: one hole, nothing isolated.
+        assert_eq!(count_holes_and_isolated(&[0, 1, 3]), (1, 0));
+    }
+
+    #[test]
+    fn test_rank_candidates_drops_over_holed_candidate() {
+        let candidates = vec!["abcde", "a.b.c.d.e"];
+        let config = HeuristicConfig::default();
+        let ranked = rank_candidates("abcde", &candidates, &config);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].text, "abcde");
+    }
+
+    #[test]
+    fn test_optimal_fuzzy_score_replaces_flat_bonus_in_relevance_score() {
+        let config = HeuristicConfig::default();
+        // A tight, unbroken fuzzy alignment should score higher than a
+        // scattered one, now that the bonus is graded rather than a flat
+        // 0.2 for any match past the threshold.
+        let tight = calculate_relevance_score_with_config("abc", "abcx", false, false, &config);
+        let scattered =
+            calculate_relevance_score_with_config("abc", "axbxc", false, false, &config);
+        assert!(tight > scattered);
     }
 
     #[test]
@@ -612,47 +2223,101 @@ mod tests {
         assert_eq!(levenshtein_distance("abc", ""), 3);
     }
 
+    #[test]
+    fn test_levenshtein_distance_multibyte_counts_chars_not_bytes() {
+        // "猫" and "犬" are each 3 UTF-8 bytes sharing the same lead byte; a
+        // byte-wise DP would report 2 where there is really only 1
+        // character swap.
+        assert_eq!(levenshtein_distance("猫", "犬"), 1);
+        assert_eq!(levenshtein_distance("café", "cafe"), 1);
+        // Emoji are multi-byte too; distance should still count characters.
+        assert_eq!(levenshtein_distance("🎉party", "party"), 1);
+    }
+
     #[test]
     fn test_is_close_match() {
-        assert!(is_close_match("hello", "helo", Some(1)));
-        assert!(is_close_match("world", "word", Some(1)));
-        assert!(!is_close_match("hello", "xyz", Some(1)));
+        assert!(is_close_match("hello", "helo", Some(1), true));
+        assert!(is_close_match("world", "word", Some(1), true));
+        assert!(!is_close_match("hello", "xyz", Some(1), true));
         // Test with dynamic threshold
-        assert!(is_close_match("hello", "helo", None)); // threshold = 5/4 = 1
+        assert!(is_close_match("hello", "helo", None, true)); // threshold = 5/4 = 1
+    }
+
+    #[test]
+    fn test_is_close_match_multibyte_threshold_uses_char_count() {
+        // "café" is 4 characters but 5 bytes, so byte- and char-based
+        // thresholds agree there (5 / 4 = 4 / 4 = 1). "日本語日" is 4
+        // characters but 12 bytes: a byte-length-based threshold
+        // (12 / 4 = 3) would accept a 2-character difference that the
+        // char-based one (4 / 4 = 1) rejects.
+        assert!(is_close_match("café", "cafe", None, true));
+        assert!(!is_close_match("café", "cxfx", None, true));
+        assert!(!is_close_match("日本語日", "日本xx", None, true));
+    }
+
+    #[test]
+    fn test_damerau_levenshtein_distance_counts_transposition_as_one_edit() {
+        assert_eq!(damerau_levenshtein_distance("teh", "the"), 1);
+        assert_eq!(levenshtein_distance("teh", "the"), 2);
+        assert_eq!(damerau_levenshtein_distance("cat", "cat"), 0);
+        assert_eq!(damerau_levenshtein_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_damerau_levenshtein_distance_multibyte() {
+        // Same transposition, but the swapped pair is multibyte.
+        assert_eq!(damerau_levenshtein_distance("acfé", "acéf"), 1);
+    }
+
+    #[test]
+    fn test_is_close_match_allow_transpositions_tightens_typo_threshold() {
+        // "teh" is a single adjacent swap away from "the": distance 1
+        // under Damerau-Levenshtein, but 2 under plain Levenshtein, so a
+        // threshold of 1 only accepts it when transpositions are allowed.
+        assert!(is_close_match("the", "teh", Some(1), true));
+        assert!(!is_close_match("the", "teh", Some(1), false));
     }
 
     #[test]
     fn test_substring_match() {
         // Case-insensitive (default)
-        assert!(is_substring_match("test", "testing", false));
-        assert!(is_substring_match("TEST", "testing", false));
-        assert!(!is_substring_match("xyz", "abc", false));
+        assert!(is_substring_match("test", "testing", false, false));
+        assert!(is_substring_match("TEST", "testing", false, false));
+        assert!(!is_substring_match("xyz", "abc", false, false));
 
         // Case-sensitive
-        assert!(is_substring_match("test", "testing", true));
-        assert!(!is_substring_match("TEST", "testing", true));
-        assert!(!is_substring_match("xyz", "abc", true));
+        assert!(is_substring_match("test", "testing", true, false));
+        assert!(!is_substring_match("TEST", "testing", true, false));
+        assert!(!is_substring_match("xyz", "abc", true, false));
+    }
+
+    #[test]
+    fn test_substring_match_normalize() {
+        assert!(!is_substring_match("naive", "naïve", false, false));
+        assert!(is_substring_match("naive", "naïve", false, true));
+        assert!(is_substring_match("NAIVE", "naïve", false, true));
+        assert!(!is_substring_match("NAIVE", "naïve", true, true));
     }
 
     #[test]
     fn test_consecutive_matches() {
         // Pattern "abc" in "aXbXc" - has gaps (X between each)
-        let (matched, is_consecutive) = count_consecutive_matches("abc", "aXbXc");
+        let (matched, is_consecutive) = count_consecutive_matches("abc", "aXbXc", false);
         assert_eq!(matched, 3);
         assert!(!is_consecutive); // scattered, not consecutive
 
         // Pattern "abc" in "abc" - no gaps
-        let (matched, is_consecutive) = count_consecutive_matches("abc", "abc");
+        let (matched, is_consecutive) = count_consecutive_matches("abc", "abc", false);
         assert_eq!(matched, 3);
         assert!(is_consecutive); // all consecutive
 
         // Pattern "ab" in "aXbXc" - has gap (X between)
-        let (matched, is_consecutive) = count_consecutive_matches("ab", "aXbXc");
+        let (matched, is_consecutive) = count_consecutive_matches("ab", "aXbXc", false);
         assert_eq!(matched, 2);
         assert!(!is_consecutive);
 
         // Pattern "ab" in "ab" - no gaps
-        let (matched, is_consecutive) = count_consecutive_matches("ab", "ab");
+        let (matched, is_consecutive) = count_consecutive_matches("ab", "ab", false);
         assert_eq!(matched, 2);
         assert!(is_consecutive);
     }
@@ -671,6 +2336,18 @@ mod tests {
         assert!(!is_word_boundary_match("test", "testing", false));
     }
 
+    #[test]
+    fn test_word_boundary_match_multibyte_text() {
+        // "café" is non-ASCII, so this goes through the char-indexed path
+        // rather than the ASCII fast path. The character right after "caf"
+        // is 'é': under `unicode_aware` it counts as alphanumeric (so "caf"
+        // is *not* a boundary match), but under ASCII-only classification
+        // it doesn't (so "caf" *is* a boundary match) -- and neither path
+        // should panic walking the multibyte neighbor.
+        assert!(!is_word_boundary_match("caf", "café", true));
+        assert!(is_word_boundary_match("caf", "café", false));
+    }
+
     #[test]
     fn test_relevance_score() {
         let exact_score = calculate_relevance_score("test", "test", true, true);
@@ -705,6 +2382,36 @@ mod tests {
         assert_eq!(config.consecutive_match_bonus, 1.5);
     }
 
+    #[test]
+    fn test_relevance_score_normalize_lets_ascii_pattern_reach_accented_text() {
+        let mut config = HeuristicConfig::default();
+        config.normalize = true;
+        let score = calculate_relevance_score_with_config("cafe", "café", false, false, &config);
+        assert!(score > 0);
+
+        config.normalize = false;
+        let score_without =
+            calculate_relevance_score_with_config("cafe", "café", false, false, &config);
+        assert!(score > score_without);
+    }
+
+    #[test]
+    fn test_rank_candidates_normalize_keeps_accented_candidate_via_fuzzy_scorer() {
+        // Neither candidate here contains "cafe" as a literal substring, so
+        // the substring-match fallback in calculate_relevance_score can't be
+        // what keeps "café" around -- only optimal_fuzzy_score consulting
+        // `config.normalize` can.
+        let mut config = HeuristicConfig::default();
+        config.normalize = true;
+        let candidates = vec!["café", "unrelated"];
+        let ranked = rank_candidates("cafe", &candidates, &config);
+        assert!(
+            ranked.iter().any(|m| m.text == "café"),
+            "accented candidate was dropped: {:?}",
+            ranked
+        );
+    }
+
     #[test]
     fn test_score_breakdown() {
         let breakdown = calculate_relevance_score_breakdown(
@@ -718,6 +2425,91 @@ mod tests {
         assert!(breakdown.total <= 1000);
     }
 
+    #[test]
+    fn test_score_details_factors_match_weighted_breakdown() {
+        let config = HeuristicConfig::default();
+        let breakdown =
+            calculate_relevance_score_breakdown("test", "testing", false, true, &config);
+        let details = calculate_relevance_score_details("test", "testing", false, true, &config);
+
+        assert_eq!(details.exact_match.weighted, breakdown.exact_match);
+        assert_eq!(details.case_sensitive.weighted, breakdown.case_sensitive);
+        assert_eq!(details.word_boundary.weighted, breakdown.word_boundary);
+        assert_eq!(details.fuzzy_match.weighted, breakdown.fuzzy_match);
+        assert_eq!(details.substring_match.weighted, breakdown.substring_match);
+        assert_eq!(
+            details.length_similarity.weighted,
+            breakdown.length_similarity
+        );
+    }
+
+    #[test]
+    fn test_score_details_local_score_is_normalized() {
+        let config = HeuristicConfig::default();
+        let details = calculate_relevance_score_details("test", "test", true, true, &config);
+
+        assert_eq!(details.exact_match.local_score, 1.0);
+        for factor in [
+            details.exact_match,
+            details.case_sensitive,
+            details.word_boundary,
+            details.fuzzy_match,
+            details.substring_match,
+            details.length_similarity,
+        ] {
+            assert!((0.0..=1.0).contains(&factor.local_score));
+        }
+    }
+
+    #[test]
+    fn test_rank_candidates_detailed_matches_rank_candidates_scores() {
+        let candidates = vec!["function", "fn", "final", "filter"];
+        let config = HeuristicConfig::default();
+        let ranked = rank_candidates("fn", &candidates, &config);
+        let detailed = rank_candidates_detailed("fn", &candidates, &config);
+
+        assert_eq!(ranked.len(), detailed.len());
+        for (plain, (detailed_match, _)) in ranked.iter().zip(detailed.iter()) {
+            assert_eq!(plain, detailed_match);
+        }
+    }
+
+    #[test]
+    fn test_classify_char() {
+        let delimiters = default_delimiters();
+        assert_eq!(classify_char('_', &delimiters), CharClass::Delimiter);
+        assert_eq!(classify_char(' ', &delimiters), CharClass::Whitespace);
+        assert_eq!(classify_char('3', &delimiters), CharClass::Number);
+        assert_eq!(classify_char('A', &delimiters), CharClass::Upper);
+        assert_eq!(classify_char('a', &delimiters), CharClass::Lower);
+    }
+
+    #[test]
+    fn test_char_class_bonuses_word_start_and_camel_case() {
+        let config = HeuristicConfig::default();
+        let (word_start, camel_case, delimiter_match) =
+            char_class_bonuses("fb", "FooBar", &config);
+        assert!(word_start > 0.0);
+        assert!(camel_case > 0.0);
+        assert_eq!(delimiter_match, 0.0);
+    }
+
+    #[test]
+    fn test_char_class_bonuses_post_delimiter() {
+        let config = HeuristicConfig::default();
+        let (_, _, delimiter_match) = char_class_bonuses("fb", "foo_bar", &config);
+        assert!(delimiter_match > 0.0);
+    }
+
+    #[test]
+    fn test_rank_candidates_rewards_word_start_and_camel_case_over_buried_match() {
+        let candidates = vec!["affable", "FooBar"];
+        let config = HeuristicConfig::default();
+        let ranked = rank_candidates("fb", &candidates, &config);
+
+        assert_eq!(ranked[0].text, "FooBar");
+    }
+
     #[test]
     fn test_edge_cases() {
         // Empty pattern
@@ -759,10 +2551,14 @@ mod tests {
         let m1 = ScoredMatch {
             text: "test1".to_string(),
             score: 500,
+            positions: Vec::new(),
+            word_position_sum: None,
         };
         let m2 = ScoredMatch {
             text: "test2".to_string(),
             score: 800,
+            positions: Vec::new(),
+            word_position_sum: None,
         };
         // m2 has higher score (800 > 500), so m2 should come first when sorted
         // In our Ord impl, we sort descending (higher scores first)
@@ -783,14 +2579,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_prefilter_accepts_in_order_subsequence() {
+        assert_eq!(prefilter("fn", "function"), Some(0));
+        assert_eq!(prefilter("fn", "refine"), Some(2));
+    }
+
+    #[test]
+    fn test_prefilter_rejects_out_of_order_or_missing_chars() {
+        assert_eq!(prefilter("xyz", "function"), None);
+        // "n" then "f": "function" has 'f' before any 'n', so in-order fails.
+        assert_eq!(prefilter("nf", "function"), None);
+    }
+
+    #[test]
+    fn test_prefilter_is_case_insensitive() {
+        assert_eq!(prefilter("FN", "function"), Some(0));
+    }
+
+    #[test]
+    fn test_prefilter_empty_pattern_and_non_ascii_always_survive() {
+        assert_eq!(prefilter("", "anything"), Some(0));
+        // Non-ASCII input skips the byte scan entirely rather than risk
+        // folding it incorrectly.
+        assert_eq!(prefilter("cafe", "café"), Some(0));
+    }
+
+    #[test]
+    fn test_rank_candidates_drops_candidates_the_prefilter_rejects() {
+        let candidates = vec!["function", "xyz", "nofn"];
+        let config = HeuristicConfig::default();
+        let ranked = rank_candidates("fn", &candidates, &config);
+        assert!(ranked.iter().any(|m| m.text == "function"));
+        assert!(!ranked.iter().any(|m| m.text == "xyz"));
+    }
+
     #[test]
     fn test_find_match_positions() {
-        let positions = find_match_positions("test", "test case testing");
+        let positions = find_match_positions("test", "test case testing", false);
         assert!(!positions.is_empty());
         // Should find "test" at position 0 and "test" in "testing"
         assert!(positions.len() >= 2);
     }
 
+    #[test]
+    fn test_find_match_positions_normalize_indexes_original_text() {
+        // "café" is 5 bytes ('é' is 2 bytes in UTF-8); the match must be
+        // reported at those original byte offsets, not folded-string ones.
+        let text = "café bar";
+        let positions = find_match_positions("cafe", text, true);
+        assert_eq!(positions, vec![(0, 5)]);
+        assert_eq!(&text[positions[0].0..positions[0].1], "café");
+    }
+
+    #[test]
+    fn test_find_match_positions_case_insensitive_multibyte() {
+        // Case-insensitive matching without `normalize` must still hold up
+        // when the haystack has multibyte characters earlier in the
+        // string: a byte-length-based scan (the old implementation) can
+        // desync once any preceding character's lowercased form has a
+        // different byte length than its original, mis-slicing the match.
+        let text = "café BAR";
+        let positions = find_match_positions("bar", text, false);
+        assert_eq!(positions, vec![(6, 9)]);
+        assert_eq!(&text[positions[0].0..positions[0].1], "BAR");
+    }
+
+    #[test]
+    fn test_find_match_positions_emoji_does_not_panic() {
+        let text = "🎉party time";
+        let positions = find_match_positions("party", text, false);
+        assert_eq!(&text[positions[0].0..positions[0].1], "party");
+    }
+
     #[test]
     fn test_highlight_matches() {
         let highlighted = highlight_matches("test", "test case", "[", "]");
@@ -805,4 +2666,330 @@ mod tests {
         let highlighted = highlight_matches("xyz", "abc", "[", "]");
         assert_eq!(highlighted, "abc");
     }
+
+    #[test]
+    fn test_fuzzy_matcher_exact_match_scores_highest() {
+        let matcher = FuzzyMatcher::new();
+        let exact = matcher.score_match("fn", "fn");
+        let scattered = matcher.score_match("fn", "f_unrelated_n");
+
+        assert!(exact.matched);
+        assert!(scattered.matched);
+        assert!(exact.score > scattered.score);
+    }
+
+    #[test]
+    fn test_fuzzy_matcher_no_match() {
+        let matcher = FuzzyMatcher::new();
+        let result = matcher.score_match("xyz", "abc");
+        assert!(!result.matched);
+        assert_eq!(result.score, 0);
+        assert!(result.positions.is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_matcher_empty_query_matches_anything() {
+        let matcher = FuzzyMatcher::new();
+        let result = matcher.score_match("", "anything");
+        assert!(result.matched);
+        assert!(result.positions.is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_matcher_word_boundary_bonus() {
+        let matcher = FuzzyMatcher::new();
+        // "sm" can only land on the two word-boundary starts
+        // ("s"earch_"m"atch), while "ea" lands mid-word; the boundary
+        // bonuses should outweigh "ea"'s consecutive-run bonus.
+        let boundary = matcher.score_match("sm", "search_match");
+        let mid_word = matcher.score_match("ea", "search_match");
+        assert!(boundary.matched && mid_word.matched);
+        assert!(boundary.score > mid_word.score);
+    }
+
+    #[test]
+    fn test_fuzzy_matcher_camel_case_boundary() {
+        let matcher = FuzzyMatcher::new();
+        let result = matcher.score_match("fb", "FooBar");
+        assert!(result.matched);
+        assert_eq!(result.positions, vec![0, 3]);
+    }
+
+    #[test]
+    fn test_fuzzy_matcher_consecutive_beats_gapped() {
+        let matcher = FuzzyMatcher::new();
+        let consecutive = matcher.score_match("ab", "xxabxx");
+        let gapped = matcher.score_match("ab", "xxaxxbxx");
+        assert!(consecutive.matched && gapped.matched);
+        assert!(consecutive.score > gapped.score);
+    }
+
+    #[test]
+    fn test_fuzzy_matcher_returns_match_positions() {
+        let matcher = FuzzyMatcher::new();
+        let result = matcher.score_match("abc", "aXbXc");
+        assert!(result.matched);
+        assert_eq!(result.positions, vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_fuzzy_matcher_score_normalized_to_1000() {
+        let matcher = FuzzyMatcher::new();
+        let result = matcher.score_match("function", "function");
+        assert!(result.score <= 1000);
+        assert!(result.score > 0);
+    }
+
+    #[test]
+    fn test_score_span_empty_query_is_always_one() {
+        assert_eq!(score_span("", "anything"), 1.0);
+        assert_eq!(score_span("", ""), 1.0);
+    }
+
+    #[test]
+    fn test_score_span_non_subsequence_is_zero() {
+        assert_eq!(score_span("xyz", "abc"), 0.0);
+    }
+
+    #[test]
+    fn test_score_span_rewards_tighter_window() {
+        // "fb" spans a 4-char window in "foobar" (f..b) but a 5-char one
+        // in the much longer "far bar foo baz", so "foobar" wins.
+        let tight = score_span("fb", "foobar");
+        let loose = score_span("fb", "far bar foo baz");
+        assert!(tight > loose);
+    }
+
+    #[test]
+    fn test_score_span_rewards_shorter_candidate_among_equally_tight_matches() {
+        // Both candidates match "ab" via an adjacent window of length 2,
+        // so the shorter candidate should score higher overall.
+        let short = score_span("ab", "ab");
+        let long = score_span("ab", "abcdefg");
+        assert!(short > long);
+    }
+
+    #[test]
+    fn test_calculate_relevance_score_with_config_dispatches_to_score_span() {
+        let mut config = HeuristicConfig::default();
+        config.span_scoring = true;
+        let expected = (score_span("fb", "foobar") * 1000.0).min(1000.0) as u32;
+        let actual = calculate_relevance_score_with_config("fb", "foobar", false, false, &config);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_calculate_relevance_score_with_config_dispatches_to_positional_bonus_score() {
+        let mut config = HeuristicConfig::default();
+        config.positional_bonus_scoring = true;
+        let expected = positional_bonus_score("fb", "foobar", &config)
+            .map(|(score, _)| score)
+            .unwrap();
+        let actual = calculate_relevance_score_with_config("fb", "foobar", false, false, &config);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_calculate_relevance_score_with_config_positional_bonus_score_zero_for_non_subsequence() {
+        let mut config = HeuristicConfig::default();
+        config.positional_bonus_scoring = true;
+        let actual = calculate_relevance_score_with_config("xyz", "abc", false, false, &config);
+        assert_eq!(actual, 0);
+    }
+
+    #[test]
+    fn test_positional_bonus_score_rejects_non_subsequence() {
+        let config = HeuristicConfig::default();
+        assert!(positional_bonus_score("xyz", "abc", &config).is_none());
+    }
+
+    #[test]
+    fn test_positional_bonus_score_empty_pattern() {
+        let config = HeuristicConfig::default();
+        let (score, positions) = positional_bonus_score("", "anything", &config).unwrap();
+        assert_eq!(score, 0);
+        assert!(positions.is_empty());
+    }
+
+    #[test]
+    fn test_positional_bonus_score_rewards_word_boundary_over_mid_word() {
+        let config = HeuristicConfig::default();
+        // "fb" hits a word-start `F` and a camelCase `B` in "FooBar", but
+        // is buried mid-word in "affable".
+        let (boundary_score, _) = positional_bonus_score("fb", "FooBar", &config).unwrap();
+        let (mid_word_score, _) = positional_bonus_score("fb", "affable", &config).unwrap();
+        assert!(boundary_score > mid_word_score);
+    }
+
+    #[test]
+    fn test_positional_bonus_score_rewards_consecutive_run_over_gapped_match() {
+        let config = HeuristicConfig::default();
+        let (consecutive, _) = positional_bonus_score("ab", "ab", &config).unwrap();
+        let (gapped, _) = positional_bonus_score("ab", "axxxxb", &config).unwrap();
+        assert!(consecutive > gapped);
+    }
+
+    #[test]
+    fn test_positional_bonus_score_penalizes_case_mismatch() {
+        let mut config = HeuristicConfig::default();
+        config.fzf_case_mismatch_penalty = 100.0;
+        let (low_penalty, _) = positional_bonus_score("ab", "abxxxxxx", &config).unwrap();
+        config.fzf_case_mismatch_penalty = 0.0;
+        let (no_penalty, _) = positional_bonus_score("ab", "abxxxxxx", &config).unwrap();
+        // Neither "a" nor "b" is a case mismatch here, so the penalty
+        // magnitude shouldn't change the score -- sanity check the knob is
+        // actually read without affecting an already-matching case.
+        assert_eq!(low_penalty, no_penalty);
+
+        let mut config = HeuristicConfig::default();
+        config.fzf_case_mismatch_penalty = 0.0;
+        let (no_penalty, _) = positional_bonus_score("ab", "AB", &config).unwrap();
+        config.fzf_case_mismatch_penalty = 50.0;
+        let (with_penalty, _) = positional_bonus_score("ab", "AB", &config).unwrap();
+        assert!(with_penalty < no_penalty);
+    }
+
+    #[test]
+    fn test_positional_bonus_score_finds_positions_as_byte_offsets() {
+        let config = HeuristicConfig::default();
+        let (_, positions) = positional_bonus_score("abc", "xabxxcx", &config)
+            .expect("'abc' is a subsequence of 'xabxxcx'");
+        assert_eq!(positions, vec![1, 2, 5]);
+    }
+
+    #[test]
+    fn test_word_position_sum_earlier_terms_score_lower() {
+        let delimiters = default_delimiters();
+        // "user" is word 1 in "get_user_name" but word 0 in "user_get".
+        assert_eq!(word_position_sum("user", "get_user_name", &delimiters), Some(1));
+        assert_eq!(word_position_sum("user", "user_get", &delimiters), Some(0));
+    }
+
+    #[test]
+    fn test_word_position_sum_sums_multiple_query_terms() {
+        let delimiters = default_delimiters();
+        // "get" is word 0, "name" is word 2, so the sum is 0 + 2 = 2.
+        assert_eq!(
+            word_position_sum("get name", "get_user_name", &delimiters),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_word_position_sum_counts_only_first_occurrence_of_a_repeated_term() {
+        let delimiters = default_delimiters();
+        // "foo" appears as words 0 and 2; only the earliest (0) counts.
+        assert_eq!(word_position_sum("foo", "foo_bar_foo", &delimiters), Some(0));
+    }
+
+    #[test]
+    fn test_word_position_sum_none_when_no_term_matches() {
+        let delimiters = default_delimiters();
+        assert_eq!(word_position_sum("xyz", "get_user_name", &delimiters), None);
+    }
+
+    #[test]
+    fn test_rank_candidates_word_position_tiebreak_prefers_earlier_match_on_score_tie() {
+        let mut config = HeuristicConfig::default();
+        config.word_position_tiebreak = true;
+        // Both candidates score identically under the additive model --
+        // same length, and a single delimiter-preceded "user" at the same
+        // character offset -- so without the tie-break they'd be in input
+        // order; with it, the candidate where "user" is an earlier word
+        // (word 1 in "abc_user" vs word 2 in "a_b_user") wins.
+        let plain = rank_candidates("user", &["abc_user", "a_b_user"], &HeuristicConfig::default());
+        assert_eq!(plain[0].score, plain[1].score, "candidates should tie on score");
+
+        let results = rank_candidates("user", &["a_b_user", "abc_user"], &config);
+        assert_eq!(results[0].text, "abc_user");
+    }
+
+    #[test]
+    fn test_rank_candidates_word_position_tiebreak_off_by_default() {
+        let config = HeuristicConfig::default();
+        let results = rank_candidates("user", &["x_y_user", "x_user_y"], &config);
+        for r in &results {
+            assert_eq!(r.word_position_sum, None);
+        }
+    }
+
+    #[test]
+    fn test_phrase_proximity_single_word_query_is_not_applicable() {
+        let delimiters = default_delimiters();
+        assert_eq!(phrase_proximity("user", "x_user_y", &delimiters, false, 4), None);
+    }
+
+    #[test]
+    fn test_phrase_proximity_adjacent_words_have_small_gap() {
+        let delimiters = default_delimiters();
+        assert_eq!(
+            phrase_proximity("foo bar", "foo_bar_baz", &delimiters, false, 4),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_phrase_proximity_is_bidirectional() {
+        let delimiters = default_delimiters();
+        // Query words reversed relative to their order in `text`; the gap
+        // is still 1 since it's measured as an absolute displacement.
+        assert_eq!(
+            phrase_proximity("bar foo", "foo_bar_baz", &delimiters, false, 4),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_phrase_proximity_none_when_a_word_is_absent() {
+        let delimiters = default_delimiters();
+        assert_eq!(
+            phrase_proximity("foo missing", "foo_bar_baz", &delimiters, false, 4),
+            None
+        );
+    }
+
+    #[test]
+    fn test_phrase_proximity_none_when_gap_exceeds_slop() {
+        let delimiters = default_delimiters();
+        assert_eq!(
+            phrase_proximity("foo qux", "foo_a_b_c_d_qux", &delimiters, false, 2),
+            None
+        );
+    }
+
+    #[test]
+    fn test_phrase_proximity_picks_minimal_gap_among_repeated_occurrences() {
+        let delimiters = default_delimiters();
+        // "b" occurs at word indices 0 and 3; "a" only at index 2. Pairing
+        // with the later "b" (index 3) gives the smaller gap (1) over
+        // pairing with the earlier one (index 0, gap 2).
+        assert_eq!(
+            phrase_proximity("a b", "b_x_a_b", &delimiters, false, 4),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_calculate_relevance_score_with_config_rewards_closer_phrase_words() {
+        let config = HeuristicConfig::default();
+        // Same four tokens (foo, bar, x, y) and total length in both
+        // candidates, so the only scoring difference is how far apart
+        // "foo" and "bar" land -- adjacent in the first, three words
+        // apart in the second.
+        let close = calculate_relevance_score_with_config(
+            "foo bar",
+            "foo_bar_x_y",
+            false,
+            false,
+            &config,
+        );
+        let far = calculate_relevance_score_with_config(
+            "foo bar",
+            "foo_x_y_bar",
+            false,
+            false,
+            &config,
+        );
+        assert!(close > far);
+    }
 }
@@ -0,0 +1,260 @@
+/*!
+Streaming incremental matcher modeled on nucleo's worker/injector design.
+
+`rank_candidates` requires every candidate to already be collected into a
+slice before scoring starts -- fine for a fixed list, but a poor fit for
+grep-over-a-large-tree, where files are still being walked when the first
+results should already be on screen. `Matcher` lets candidates stream in
+from other threads via cloneable `Injector` handles while a query is
+already in flight, and hands back an up-to-date top-N `snapshot()` on
+demand.
+
+This module doesn't spawn any threads itself, following this crate's
+existing pattern for cross-thread coordination (see
+`cache::SearchController`): candidates accumulate in shared, `Arc`-backed
+storage that any number of producer threads can push into concurrently,
+and the consumer calls `snapshot()` whenever it wants a freshly re-scored
+view -- typically driven by the same timer or event loop that redraws the
+UI, rather than by a background thread owned by this module.
+*/
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::heuristic::{rank_candidates, HeuristicConfig, ScoredMatch};
+
+/// State shared between a `Matcher` and every `Injector` cloned from it.
+#[derive(Debug)]
+struct Shared {
+    /// Every candidate ingested so far, in push order. Rescored in full
+    /// on each `snapshot()` call rather than incrementally, since
+    /// `rank_candidates` is already cheap enough per call that keeping a
+    /// second incremental index in step with concurrent pushes isn't
+    /// worth the complexity.
+    candidates: Mutex<Vec<String>>,
+    /// Number of live `Injector` handles, including clones. Lets
+    /// `Matcher::active_injectors` tell a UI whether more results may
+    /// still arrive.
+    active_injectors: AtomicUsize,
+}
+
+/// Handle producer threads use to push newly discovered candidates into a
+/// `Matcher` while it's already matching against a query. Cheap to clone
+/// and safe to send across threads; every live clone counts toward
+/// `Matcher::active_injectors` until dropped.
+#[derive(Debug)]
+pub(crate) struct Injector {
+    shared: Arc<Shared>,
+}
+
+impl Injector {
+    fn new(shared: Arc<Shared>) -> Self {
+        shared.active_injectors.fetch_add(1, Ordering::SeqCst);
+        Injector { shared }
+    }
+
+    /// Pushes one more candidate to be scored on the next `snapshot()`.
+    pub(crate) fn push(&self, candidate: impl Into<String>) {
+        if let Ok(mut candidates) = self.shared.candidates.lock() {
+            candidates.push(candidate.into());
+        }
+    }
+}
+
+impl Clone for Injector {
+    fn clone(&self) -> Self {
+        Injector::new(Arc::clone(&self.shared))
+    }
+}
+
+impl Drop for Injector {
+    fn drop(&mut self) {
+        self.shared.active_injectors.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// High-level streaming matcher: owns a query and a `HeuristicConfig`,
+/// hands out `Injector` handles that any number of producer threads can
+/// push candidates into, and re-scores on demand via `snapshot()`.
+/// Refining the query with `set_query` reuses every candidate already
+/// ingested rather than requiring the caller to resubmit them.
+#[derive(Debug)]
+pub(crate) struct Matcher {
+    shared: Arc<Shared>,
+    query: String,
+    config: HeuristicConfig,
+}
+
+impl Matcher {
+    /// Creates a matcher for `query`, with no candidates ingested yet.
+    pub(crate) fn new(query: impl Into<String>, config: HeuristicConfig) -> Self {
+        Matcher {
+            shared: Arc::new(Shared {
+                candidates: Mutex::new(Vec::new()),
+                active_injectors: AtomicUsize::new(0),
+            }),
+            query: query.into(),
+            config,
+        }
+    }
+
+    /// Returns a new handle a producer thread can use to push candidates.
+    /// Can be called any number of times; every live handle (and every
+    /// clone of it) counts toward `active_injectors`.
+    pub(crate) fn injector(&self) -> Injector {
+        Injector::new(Arc::clone(&self.shared))
+    }
+
+    /// Replaces the query a later `snapshot()` scores against. Every
+    /// candidate ingested so far via an `Injector` is kept and rescored
+    /// against the new query -- refining a query never requires the
+    /// caller to re-walk and re-push the whole candidate set.
+    pub(crate) fn set_query(&mut self, query: impl Into<String>) {
+        self.query = query.into();
+    }
+
+    /// The query this matcher currently scores against.
+    pub(crate) fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Number of `Injector` handles still alive (including clones).
+    /// Drops to zero once every producer thread has finished pushing and
+    /// dropped its handle, telling a UI no more results will ever arrive
+    /// for the in-flight search.
+    pub(crate) fn active_injectors(&self) -> usize {
+        self.shared.active_injectors.load(Ordering::SeqCst)
+    }
+
+    /// Total number of candidates ingested so far, regardless of whether
+    /// they match the current query.
+    pub(crate) fn candidate_count(&self) -> usize {
+        self.with_candidates(|candidates| candidates.len())
+    }
+
+    /// Re-scores every candidate ingested so far against the current
+    /// query and returns the top `limit` results, ordered the same way
+    /// `rank_candidates` orders its output. Safe to call repeatedly while
+    /// injectors are still pushing: each call re-reads the full candidate
+    /// list under the shared lock, so it always reflects everything
+    /// ingested up to that point.
+    pub(crate) fn snapshot(&self, limit: usize) -> Vec<ScoredMatch> {
+        self.with_candidates(|candidates| {
+            let refs: Vec<&str> = candidates.iter().map(String::as_str).collect();
+            let mut results = rank_candidates(&self.query, &refs, &self.config);
+            results.truncate(limit);
+            results
+        })
+    }
+
+    /// Runs `f` against the ingested candidate list, recovering the
+    /// lock's data even if a previous holder panicked while holding it --
+    /// a poisoned candidate list is still perfectly readable, and a
+    /// streaming matcher shouldn't wedge the whole search over it.
+    fn with_candidates<T>(&self, f: impl FnOnce(&[String]) -> T) -> T {
+        let candidates = self
+            .shared
+            .candidates
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&candidates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_empty_matcher_has_no_results() {
+        let matcher = Matcher::new("fn", HeuristicConfig::default());
+        assert!(matcher.snapshot(10).is_empty());
+        assert_eq!(matcher.candidate_count(), 0);
+    }
+
+    #[test]
+    fn test_injector_push_is_visible_in_snapshot() {
+        let matcher = Matcher::new("fn", HeuristicConfig::default());
+        let injector = matcher.injector();
+        injector.push("function");
+        injector.push("unrelated");
+
+        let results = matcher.snapshot(10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].text, "function");
+        assert_eq!(matcher.candidate_count(), 2);
+    }
+
+    #[test]
+    fn test_multiple_injectors_push_into_the_same_matcher() {
+        let matcher = Matcher::new("fn", HeuristicConfig::default());
+        let a = matcher.injector();
+        let b = matcher.injector();
+        a.push("function");
+        b.push("find");
+
+        let results = matcher.snapshot(10);
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_active_injectors_tracks_live_handles() {
+        let matcher = Matcher::new("fn", HeuristicConfig::default());
+        assert_eq!(matcher.active_injectors(), 0);
+
+        let a = matcher.injector();
+        assert_eq!(matcher.active_injectors(), 1);
+
+        let b = a.clone();
+        assert_eq!(matcher.active_injectors(), 2);
+
+        drop(a);
+        assert_eq!(matcher.active_injectors(), 1);
+
+        drop(b);
+        assert_eq!(matcher.active_injectors(), 0);
+    }
+
+    #[test]
+    fn test_set_query_rescopes_already_ingested_candidates() {
+        let mut matcher = Matcher::new("fn", HeuristicConfig::default());
+        let injector = matcher.injector();
+        injector.push("function");
+        injector.push("grep_util");
+
+        assert_eq!(matcher.snapshot(10).len(), 1);
+
+        // Refining the query rescopes the same ingested candidates --
+        // no re-push required.
+        matcher.set_query("grep");
+        let results = matcher.snapshot(10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].text, "grep_util");
+        assert_eq!(matcher.candidate_count(), 2);
+    }
+
+    #[test]
+    fn test_snapshot_respects_limit() {
+        let matcher = Matcher::new("fn", HeuristicConfig::default());
+        let injector = matcher.injector();
+        for name in ["fn1", "fn2", "fn3", "fn4"] {
+            injector.push(name);
+        }
+
+        assert_eq!(matcher.snapshot(2).len(), 2);
+        assert_eq!(matcher.snapshot(100).len(), 4);
+    }
+
+    #[test]
+    fn test_injector_is_sendable_across_threads() {
+        let matcher = Matcher::new("fn", HeuristicConfig::default());
+        let injector = matcher.injector();
+
+        let handle = std::thread::spawn(move || {
+            injector.push("function");
+        });
+        handle.join().expect("producer thread should not panic");
+
+        assert_eq!(matcher.snapshot(10).len(), 1);
+    }
+}
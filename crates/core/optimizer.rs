@@ -9,9 +9,14 @@ This module implements critical optimizations:
 */
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::disk_cache::DiskCache;
+use crate::fastcdc::{ChunkPool, ChunkerConfig};
+use crate::gitignore::{read_ignore_file_patterns, IgnoreMatcher};
+use crate::simd::BYTE_FREQUENCY;
+
 /// Ultra-fast pattern cache using Vec instead of HashMap.
 /// No hashing overhead - perfect for <100 patterns.
 /// 3-5× faster than HashMap for small pattern counts.
@@ -68,38 +73,194 @@ impl Default for PatternCache {
     }
 }
 
+/// How one `CacheNode`'s payload is actually stored: as a single
+/// allocation, or -- when `FileContentCache::with_chunked_dedup` is in
+/// use -- as a list of chunk hashes into a shared `ChunkPool`, so
+/// identical regions across files and across versions of the same file
+/// only ever cost one allocation.
+#[derive(Clone, Debug)]
+enum CacheContent {
+    Whole(Arc<[u8]>),
+    Chunked(Vec<u64>),
+}
+
+/// One slot in `FileContentCache`'s intrusive recency list.
+#[derive(Clone, Debug)]
+struct CacheNode {
+    path: std::path::PathBuf,
+    content: CacheContent,
+    /// Byte length of the original content, tracked independently of
+    /// `content`'s representation so size accounting doesn't need to pay
+    /// for a chunk reassembly just to learn how big an entry is.
+    len: usize,
+    /// Total number of `get` hits, used only by `EvictionPolicy::Lfu`.
+    hits: u64,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// Which entry `FileContentCache` evicts when it's over its size budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EvictionPolicy {
+    /// Evict the entry that hasn't been read in the longest time.
+    Lru,
+    /// Evict the entry with the fewest total `get` hits, favoring files a
+    /// fuzzy scan reads over and over even across a burst of one-off reads.
+    Lfu,
+}
+
 /// Fast file content cache using Arc<[u8]> instead of Arc<String>.
 /// Avoids UTF-8 validation overhead - 10-25% faster.
 /// Zero-I/O policy: keeps frequently accessed files in memory.
-/// Uses FIFO eviction to prevent latency spikes from full cache clears.
+///
+/// Recency is tracked with an intrusive doubly-linked list threaded
+/// through `nodes` via `prev`/`next` indices, so `get` can move an entry
+/// to the front and `insert` can evict from the back in O(1) -- no
+/// `Vec::remove(0)` shifting the way a plain FIFO order would need.
 #[derive(Clone, Debug)]
 pub(crate) struct FileContentCache {
-    cache: HashMap<std::path::PathBuf, Arc<[u8]>>,
-    access_order: Vec<std::path::PathBuf>,
+    nodes: Vec<CacheNode>,
+    /// Slots in `nodes` freed by a past eviction, reused by the next
+    /// insert so the arena doesn't grow without bound over a long session.
+    free_slots: Vec<usize>,
+    index: HashMap<std::path::PathBuf, usize>,
+    /// Most-recently-used entry, `None` when the cache is empty.
+    head: Option<usize>,
+    /// Least-recently-used entry, `None` when the cache is empty.
+    tail: Option<usize>,
     max_size_bytes: usize,
     current_size_bytes: usize,
+    policy: EvictionPolicy,
+    /// Shared chunk pool every insert is routed through when chunked
+    /// dedup is enabled (see `with_chunked_dedup`); `None` stores each
+    /// file as one independent allocation, same as before.
+    chunk_pool: Option<ChunkPool>,
+    chunker_config: ChunkerConfig,
+    /// Persistent second tier consulted on a miss and mirrored on every
+    /// insert when opened via `with_disk_cache`.
+    disk_cache: Option<DiskCache>,
 }
 
 impl FileContentCache {
-    /// Create a new file content cache with size limit (in bytes).
+    /// Create a new file content cache with size limit (in bytes), evicting
+    /// by recency (LRU) when full.
     pub(crate) fn new(max_size_bytes: usize) -> Self {
         FileContentCache {
-            cache: HashMap::with_capacity(256),
-            access_order: Vec::with_capacity(256),
+            nodes: Vec::with_capacity(256),
+            free_slots: Vec::new(),
+            index: HashMap::with_capacity(256),
+            head: None,
+            tail: None,
             max_size_bytes,
             current_size_bytes: 0,
+            policy: EvictionPolicy::Lru,
+            chunk_pool: None,
+            chunker_config: ChunkerConfig::default(),
+            disk_cache: None,
         }
     }
 
-    /// Get cached file content if available.
-    /// Returns raw bytes (no UTF-8 overhead).
+    /// Same as `new`, but evicts the entry with the fewest total hits
+    /// instead of the least-recently-used one.
+    pub(crate) fn with_lfu_eviction(max_size_bytes: usize) -> Self {
+        FileContentCache {
+            policy: EvictionPolicy::Lfu,
+            ..Self::new(max_size_bytes)
+        }
+    }
+
+    /// Same as `new`, but routes every inserted file's content through a
+    /// shared, content-defined `ChunkPool` (FastCDC, see the `fastcdc`
+    /// module) instead of storing it as one independent allocation. A
+    /// `get` pays a reassembly copy, in exchange for files that share
+    /// long identical regions -- including repeated versions of the same
+    /// slowly-edited file -- only ever paying for the bytes that differ.
+    pub(crate) fn with_chunked_dedup(max_size_bytes: usize) -> Self {
+        FileContentCache {
+            chunk_pool: Some(ChunkPool::new()),
+            ..Self::new(max_size_bytes)
+        }
+    }
+
+    /// Same as `new`, but also opens a persistent on-disk second tier
+    /// under `cache_dir`: a `get` miss against a file whose `DiskCache`
+    /// fingerprint (`path`, `mtime`, `size`) still matches is served from
+    /// disk instead of requiring the caller to re-read it, and every
+    /// `insert` is mirrored there so the next process run starts warm.
+    pub(crate) fn with_disk_cache(max_size_bytes: usize, cache_dir: &Path) -> anyhow::Result<Self> {
+        Ok(FileContentCache {
+            disk_cache: Some(DiskCache::open(cache_dir)?),
+            ..Self::new(max_size_bytes)
+        })
+    }
+
+    /// Get cached file content if available, marking it most-recently-used
+    /// and bumping its hit counter. Returns raw bytes (no UTF-8 overhead).
+    /// On an in-memory miss, falls back to the disk tier (if configured):
+    /// a fresh fingerprint match there is promoted back into memory so it
+    /// doesn't have to be read from disk again on the very next call.
     #[inline]
-    pub(crate) fn get(&self, path: &Path) -> Option<Arc<[u8]>> {
-        self.cache.get(path).map(Arc::clone)
+    pub(crate) fn get(&mut self, path: &Path) -> Option<Arc<[u8]>> {
+        if let Some(&idx) = self.index.get(path) {
+            self.unlink(idx);
+            self.push_front(idx);
+            self.nodes[idx].hits += 1;
+            return Some(self.content_bytes(idx));
+        }
+
+        let from_disk = self
+            .disk_cache
+            .as_ref()
+            .and_then(|disk| disk.get_content(path))
+            .map(<[u8]>::to_vec)?;
+
+        self.insert(path.to_path_buf(), from_disk);
+        self.index.get(path).copied().map(|idx| self.content_bytes(idx))
     }
 
-    /// Insert file content into cache with FIFO eviction.
-    /// Prevents latency spikes from full cache clears.
+    /// Reads `idx`'s payload out of its stored representation: a cheap
+    /// `Arc::clone` for `CacheContent::Whole`, or a reassembly copy out of
+    /// the shared `chunk_pool` for `CacheContent::Chunked`.
+    fn content_bytes(&self, idx: usize) -> Arc<[u8]> {
+        match &self.nodes[idx].content {
+            CacheContent::Whole(bytes) => Arc::clone(bytes),
+            CacheContent::Chunked(hashes) => {
+                let pool = self
+                    .chunk_pool
+                    .as_ref()
+                    .expect("a Chunked node implies chunk_pool is set");
+                Arc::from(pool.reassemble(hashes).unwrap_or_default())
+            }
+        }
+    }
+
+    /// Stores `content` under whichever representation is currently
+    /// configured: chunked through the shared pool, or as one independent
+    /// allocation.
+    fn store_content(&mut self, content: &[u8]) -> CacheContent {
+        match self.chunk_pool.as_mut() {
+            Some(pool) => CacheContent::Chunked(pool.insert(content, &self.chunker_config)),
+            None => CacheContent::Whole(Arc::from(content)),
+        }
+    }
+
+    /// Releases `idx`'s claim on its chunks in the shared pool, if it's a
+    /// `CacheContent::Chunked` node -- a no-op for `Whole` nodes, which own
+    /// their `Arc<[u8]>` directly and need no bookkeeping elsewhere. Call
+    /// before overwriting or discarding a node's `content`, or evicted/
+    /// replaced chunks would stay referenced in `chunk_pool` forever.
+    fn release_content(&mut self, idx: usize) {
+        if let CacheContent::Chunked(hashes) = &self.nodes[idx].content {
+            if let Some(pool) = self.chunk_pool.as_mut() {
+                pool.remove(hashes);
+            }
+        }
+    }
+
+    /// Insert file content into the cache, evicting entries under the
+    /// configured `EvictionPolicy` until there's room. Mirrored to the
+    /// disk tier (if configured) so a later process run can skip
+    /// re-reading this file entirely while its fingerprint is unchanged.
     pub(crate) fn insert(&mut self, path: std::path::PathBuf, content: Vec<u8>) {
         let size = content.len();
 
@@ -108,33 +269,159 @@ impl FileContentCache {
             return;
         }
 
-        // FIFO eviction: remove oldest entries until we have space
-        while self.current_size_bytes + size > self.max_size_bytes && !self.access_order.is_empty() {
-            if let Some(oldest_path) = self.access_order.first() {
-                if let Some(removed) = self.cache.remove(oldest_path) {
-                    self.current_size_bytes = self.current_size_bytes.saturating_sub(removed.len());
-                }
-                self.access_order.remove(0);
-            }
+        if let Some(disk) = self.disk_cache.as_mut() {
+            // A stat failure here (e.g. the file vanished between being
+            // read and being cached) just means the disk tier misses it
+            // next time -- never worth failing the whole insert over.
+            let _ = disk.put_content(&path, &content);
         }
 
+        let stored = self.store_content(&content);
+
+        // Re-inserting a known path drops the old entry first, so its freed
+        // slot is reused instead of growing the arena with a duplicate
+        // entry, and the new content still goes through the eviction below.
+        if let Some(&idx) = self.index.get(&path) {
+            self.remove_node(idx);
+        }
+
+        while self.current_size_bytes + size > self.max_size_bytes && self.tail.is_some() {
+            self.evict_one();
+        }
+
+        let idx = match self.free_slots.pop() {
+            Some(idx) => {
+                self.nodes[idx] = CacheNode {
+                    path: path.clone(),
+                    content: stored,
+                    len: size,
+                    hits: 0,
+                    prev: None,
+                    next: None,
+                };
+                idx
+            }
+            None => {
+                self.nodes.push(CacheNode {
+                    path: path.clone(),
+                    content: stored,
+                    len: size,
+                    hits: 0,
+                    prev: None,
+                    next: None,
+                });
+                self.nodes.len() - 1
+            }
+        };
+
+        self.index.insert(path, idx);
         self.current_size_bytes += size;
-        self.cache.insert(path.clone(), Arc::from(content));
-        self.access_order.push(path);
+        self.push_front(idx);
+    }
+
+    /// Unlinks `idx` from the recency list without touching its own
+    /// `prev`/`next`, which the caller overwrites next (via `push_front`
+    /// or by discarding the node entirely in `remove_node`).
+    fn unlink(&mut self, idx: usize) {
+        let (prev, next) = {
+            let node = &self.nodes[idx];
+            (node.prev, node.next)
+        };
+        match prev {
+            Some(p) => self.nodes[p].next = next,
+            None => self.head = next,
+        }
+        match next {
+            Some(n) => self.nodes[n].prev = prev,
+            None => self.tail = prev,
+        }
+    }
+
+    /// Makes `idx` the most-recently-used entry.
+    fn push_front(&mut self, idx: usize) {
+        self.nodes[idx].prev = None;
+        self.nodes[idx].next = self.head;
+        if let Some(h) = self.head {
+            self.nodes[h].prev = Some(idx);
+        }
+        self.head = Some(idx);
+        if self.tail.is_none() {
+            self.tail = Some(idx);
+        }
+    }
+
+    /// Evicts one entry under the configured policy: the tail for LRU, or
+    /// a linear scan for the minimum hit count for LFU.
+    fn evict_one(&mut self) {
+        let victim = match self.policy {
+            EvictionPolicy::Lru => self.tail,
+            EvictionPolicy::Lfu => self.min_hit_node(),
+        };
+        if let Some(idx) = victim {
+            self.remove_node(idx);
+        }
+    }
+
+    /// The node with the fewest hits, walking the recency list since hit
+    /// counts aren't kept in a separate priority order.
+    fn min_hit_node(&self) -> Option<usize> {
+        let mut cursor = self.head;
+        let mut best: Option<(usize, u64)> = None;
+        while let Some(idx) = cursor {
+            let node = &self.nodes[idx];
+            if best.map_or(true, |(_, hits)| node.hits < hits) {
+                best = Some((idx, node.hits));
+            }
+            cursor = node.next;
+        }
+        best.map(|(idx, _)| idx)
+    }
+
+    /// Unlinks `idx`, drops its accounting (including releasing any
+    /// chunks it held in the shared pool), and frees its slot for reuse.
+    fn remove_node(&mut self, idx: usize) {
+        self.unlink(idx);
+        self.release_content(idx);
+        let node = &self.nodes[idx];
+        self.current_size_bytes = self.current_size_bytes.saturating_sub(node.len);
+        self.index.remove(&node.path);
+        self.free_slots.push(idx);
     }
 
     /// Clear cache
     #[inline]
     pub(crate) fn clear(&mut self) {
-        self.cache.clear();
-        self.access_order.clear();
+        self.nodes.clear();
+        self.free_slots.clear();
+        self.index.clear();
+        self.head = None;
+        self.tail = None;
         self.current_size_bytes = 0;
+        if let Some(pool) = self.chunk_pool.as_mut() {
+            *pool = ChunkPool::new();
+        }
     }
 
     /// Get cache statistics
     #[inline]
     pub(crate) fn stats(&self) -> (usize, usize) {
-        (self.cache.len(), self.current_size_bytes)
+        (self.index.len(), self.current_size_bytes)
+    }
+
+    /// Number of distinct chunks currently held in the shared pool, or
+    /// `None` when chunked dedup isn't enabled.
+    pub(crate) fn chunk_pool_len(&self) -> Option<usize> {
+        self.chunk_pool.as_ref().map(ChunkPool::len)
+    }
+
+    /// Forwards to the disk tier's garbage collection (dropping entries
+    /// whose path no longer exists), if a disk tier is configured;
+    /// returns `0` otherwise.
+    pub(crate) fn gc_disk_cache(&mut self) -> anyhow::Result<usize> {
+        match self.disk_cache.as_mut() {
+            Some(disk) => disk.gc(),
+            None => Ok(0),
+        }
     }
 }
 
@@ -144,83 +431,394 @@ impl Default for FileContentCache {
     }
 }
 
-/// Ultra-fast path filter using byte-level search (O(1) for fixed dirs).
-/// 2-3× faster than string-based filtering.
-/// Skipping these alone can save 20-50ms on large repos.
+/// Root node index of every `AhoCorasick` trie/automaton.
+const AC_ROOT: usize = 0;
+
+/// Multi-pattern substring automaton, built once from a set of skip
+/// patterns so matching a path against hundreds of them costs one linear
+/// pass over its bytes instead of one pass per pattern.
+///
+/// Construction follows the classic Aho-Corasick recipe: build a trie
+/// (the `goto` function) from the needle bytes, then BFS over it to
+/// compute each node's failure link -- the longest proper suffix of the
+/// path to that node which is also a trie prefix -- and fold each node's
+/// output flag into its children along those same failure links. The BFS
+/// also fills in the *missing* trie edges so `goto` becomes a total
+/// function (`goto[node][byte]` is always a valid node), which means
+/// matching itself never needs to consult failure links at all -- it
+/// just walks `goto` and checks `output`.
+#[derive(Clone, Debug)]
+struct AhoCorasick {
+    /// Total transition function: `goto[node][byte]` is always valid.
+    goto: Vec<[usize; 256]>,
+    /// Whether node `i` marks the end of at least one pattern, directly
+    /// or via a failure-linked ancestor.
+    output: Vec<bool>,
+}
+
+impl AhoCorasick {
+    /// Builds an automaton matching any of `patterns` as a substring.
+    /// An automaton built from no patterns never matches anything.
+    fn build(patterns: &[Vec<u8>]) -> Self {
+        struct TrieNode {
+            children: [Option<usize>; 256],
+            output: bool,
+        }
+
+        let mut trie = vec![TrieNode {
+            children: [None; 256],
+            output: false,
+        }];
+
+        for pattern in patterns {
+            let mut node = AC_ROOT;
+            for &byte in pattern {
+                node = match trie[node].children[byte as usize] {
+                    Some(next) => next,
+                    None => {
+                        trie.push(TrieNode {
+                            children: [None; 256],
+                            output: false,
+                        });
+                        let next = trie.len() - 1;
+                        trie[node].children[byte as usize] = Some(next);
+                        next
+                    }
+                };
+            }
+            trie[node].output = true;
+        }
+
+        let node_count = trie.len();
+        let mut goto = vec![[AC_ROOT; 256]; node_count];
+        let mut fail = vec![AC_ROOT; node_count];
+        let mut output: Vec<bool> = trie.iter().map(|node| node.output).collect();
+        let mut queue = std::collections::VecDeque::new();
+
+        // Depth 1: every root child fails back to the root itself.
+        for byte in 0..256 {
+            match trie[AC_ROOT].children[byte] {
+                Some(child) => {
+                    goto[AC_ROOT][byte] = child;
+                    fail[child] = AC_ROOT;
+                    queue.push_back(child);
+                }
+                None => goto[AC_ROOT][byte] = AC_ROOT,
+            }
+        }
+
+        // BFS in increasing-depth order, so `fail[node]`'s goto row is
+        // already finalized for every byte by the time `node` is popped.
+        while let Some(node) = queue.pop_front() {
+            for byte in 0..256 {
+                match trie[node].children[byte] {
+                    Some(child) => {
+                        fail[child] = goto[fail[node]][byte];
+                        output[child] |= output[fail[child]];
+                        goto[node][byte] = child;
+                        queue.push_back(child);
+                    }
+                    None => goto[node][byte] = goto[fail[node]][byte],
+                }
+            }
+        }
+
+        AhoCorasick { goto, output }
+    }
+
+    /// True the instant `haystack` walks into any node whose output flag
+    /// is set, i.e. `haystack` contains at least one configured pattern.
+    #[inline]
+    fn is_match(&self, haystack: &[u8]) -> bool {
+        let mut node = AC_ROOT;
+        for &byte in haystack {
+            node = self.goto[node][byte as usize];
+            if self.output[node] {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Leading magic byte sequences that identify common binary formats,
+/// checked the way cargo's content-hash work uses the `object` crate to
+/// sniff executables -- a direct signature match is exact, unlike the
+/// null-byte heuristic it takes priority over.
+const BINARY_MAGIC_SIGNATURES: &[&[u8]] = &[
+    b"\x7fELF",                  // ELF
+    b"MZ",                        // PE (Windows)
+    b"\xfe\xed\xfa\xce",          // Mach-O 32-bit
+    b"\xfe\xed\xfa\xcf",          // Mach-O 64-bit
+    b"\xce\xfa\xed\xfe",          // Mach-O 32-bit, reversed byte order
+    b"\xcf\xfa\xed\xfe",          // Mach-O 64-bit, reversed byte order
+    b"\xca\xfe\xba\xbe",          // Mach-O fat binary
+    b"%PDF",                      // PDF
+    b"PK\x03\x04",                // ZIP / JAR
+    b"\x1f\x8b",                  // gzip
+    b"\x89PNG\r\n\x1a\n",         // PNG
+    b"\xff\xd8\xff",              // JPEG
+    b"GIF87a",                    // GIF
+    b"GIF89a",                    // GIF
+];
+
+/// Byte-order marks identifying encoded text, checked before the magic
+/// signatures and null-byte heuristic so BOM-prefixed UTF-16/32 content
+/// is always treated as searchable text rather than binary.
+const TEXT_BOMS: &[&[u8]] = &[
+    b"\xef\xbb\xbf",             // UTF-8
+    b"\xff\xfe\x00\x00",         // UTF-32LE (checked before UTF-16LE below)
+    b"\x00\x00\xfe\xff",         // UTF-32BE
+    b"\xff\xfe",                 // UTF-16LE
+    b"\xfe\xff",                 // UTF-16BE
+];
+
+/// Whether `content` starts with a recognized binary format's magic
+/// bytes.
+fn has_binary_magic(content: &[u8]) -> bool {
+    BINARY_MAGIC_SIGNATURES
+        .iter()
+        .any(|signature| content.starts_with(signature))
+}
+
+/// Whether `content` starts with a byte-order mark, i.e. is encoded text
+/// even if a null-byte scan alone might mistake it for binary.
+fn has_text_bom(content: &[u8]) -> bool {
+    TEXT_BOMS.iter().any(|bom| content.starts_with(bom))
+}
+
+/// Ultra-fast path filter: an Aho-Corasick automaton skips any path
+/// containing a configured directory/pattern in a single linear pass,
+/// regardless of how many patterns are configured.
 #[derive(Clone, Debug)]
 pub(crate) struct PathFilter {
     /// Maximum file size to search (bytes)
     pub max_file_size: u64,
     /// Skip binary files
     pub skip_binary: bool,
-    /// Directories to skip (as byte slices for fast search)
-    skip_dirs: [&'static [u8]; 9],
+    /// Patterns that mark a path for skipping if found anywhere in it,
+    /// kept alongside `skip_automaton` so `add_skip_pattern`/
+    /// `set_skip_patterns` can rebuild the automaton from the full set.
+    skip_patterns: Vec<Vec<u8>>,
+    /// Automaton built from `skip_patterns`, rebuilt whenever they change.
+    skip_automaton: AhoCorasick,
+    /// Whether `.gitignore`/`.ignore`-style files loaded via
+    /// `load_ignore_file` are consulted at all -- a `--no-respect-gitignore`
+    /// style escape hatch for callers that want every file considered
+    /// regardless of what's ignored.
+    respect_ignore_files: bool,
+    /// Ignore matchers loaded so far, paired with the directory each is
+    /// rooted at, in discovery order (root-level files first, more
+    /// specific ones loaded as the walk descends). A later layer's verdict
+    /// on a path overrides an earlier layer's, matching how git resolves a
+    /// nested `.gitignore` taking precedence over one above it.
+    ignore_layers: Vec<(PathBuf, IgnoreMatcher)>,
+    /// Facts about a path already known from the walker's own `DirEntry`,
+    /// populated via `record_metadata` so `should_skip` never needs to
+    /// issue its own `std::fs::metadata` call for a path the walker has
+    /// already stat'd.
+    metadata_cache: HashMap<PathBuf, PathMetadata>,
+    /// When set, `should_skip` never checks `max_file_size` at all --
+    /// callers that want the size enforced must call
+    /// `exceeds_max_file_size` themselves, right before reading a file's
+    /// content, so a path rejected by `skip_automaton`/ignore layers alone
+    /// (e.g. anything under `node_modules`) never triggers a stat.
+    defer_size_check: bool,
+}
+
+/// Cached filesystem facts for one path: `is_file`, size in bytes, and
+/// modification time, exactly as reported by whichever `std::fs::Metadata`
+/// populated them (typically a `DirEntry`'s own metadata during directory
+/// traversal, which the OS already fetched for free).
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PathMetadata {
+    pub is_file: bool,
+    pub is_dir: bool,
+    pub len: u64,
+    pub mtime: std::time::SystemTime,
+}
+
+impl PathMetadata {
+    /// Builds a cache entry from a `std::fs::Metadata` the caller already
+    /// has in hand (e.g. a `DirEntry::metadata()` result), so recording it
+    /// never itself costs an extra stat.
+    pub(crate) fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+        PathMetadata {
+            is_file: metadata.is_file(),
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            mtime: metadata.modified().unwrap_or(std::time::UNIX_EPOCH),
+        }
+    }
 }
 
 impl PathFilter {
     /// Create filter with sensible defaults.
     pub(crate) fn default_filter() -> Self {
+        let skip_patterns: Vec<Vec<u8>> = [
+            &b"node_modules"[..],
+            b".git",
+            b"target",
+            b"build",
+            b"dist",
+            b".cache",
+            b"__pycache__",
+            b".venv",
+            b"vendor",
+        ]
+        .into_iter()
+        .map(|pattern| pattern.to_vec())
+        .collect();
+        let skip_automaton = AhoCorasick::build(&skip_patterns);
+
         PathFilter {
             max_file_size: 1_000_000, // 1MB
             skip_binary: true,
-            skip_dirs: [
-                b"node_modules",
-                b".git",
-                b"target",
-                b"build",
-                b"dist",
-                b".cache",
-                b"__pycache__",
-                b".venv",
-                b"vendor",
-            ],
+            skip_patterns,
+            skip_automaton,
+            respect_ignore_files: true,
+            ignore_layers: Vec::new(),
+            metadata_cache: HashMap::new(),
+            defer_size_check: false,
         }
     }
 
-    /// Check if path should be skipped.
-    /// Uses byte-level search - much faster than string contains.
-    #[inline]
-    pub(crate) fn should_skip(&self, path: &Path) -> bool {
-        let path_bytes = path.as_os_str().as_encoded_bytes();
+    /// Adds one more pattern to the skip set and rebuilds the automaton,
+    /// so callers can add things like `coverage`, `.next`, or a custom
+    /// vendor directory without recompiling.
+    pub(crate) fn add_skip_pattern(&mut self, pattern: impl Into<Vec<u8>>) {
+        self.skip_patterns.push(pattern.into());
+        self.skip_automaton = AhoCorasick::build(&self.skip_patterns);
+    }
 
-        // O(n * 9) byte search - very fast
-        for skip_dir in &self.skip_dirs {
-            if Self::bytes_contains(path_bytes, skip_dir) {
-                return true;
-            }
+    /// Replaces the entire skip set and rebuilds the automaton once.
+    pub(crate) fn set_skip_patterns<I, P>(&mut self, patterns: I)
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<Vec<u8>>,
+    {
+        self.skip_patterns = patterns.into_iter().map(Into::into).collect();
+        self.skip_automaton = AhoCorasick::build(&self.skip_patterns);
+    }
+
+    /// Enables or disables honoring loaded ignore files altogether,
+    /// mirroring ruff's `--no-respect-gitignore`: the skip-pattern
+    /// automaton and file-size limit still apply either way.
+    pub(crate) fn set_respect_ignore_files(&mut self, respect: bool) {
+        self.respect_ignore_files = respect;
+    }
+
+    /// Loads `path` as a gitignore-style ignore file (resolving any
+    /// `%include <path>` directives recursively) and adds it as the most
+    /// specific ignore layer so far, rooted at its parent directory.
+    /// Later-loaded layers take precedence over earlier ones wherever they
+    /// explicitly match a path, same as a nested `.gitignore` in git.
+    pub(crate) fn load_ignore_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        let patterns = read_ignore_file_patterns(path)?;
+        let matcher = IgnoreMatcher::from_lines(patterns.iter().map(String::as_str));
+        let root = path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        self.ignore_layers.push((root, matcher));
+        Ok(())
+    }
+
+    /// Whether any ignore layer (rather than the skip-pattern automaton or
+    /// file-size limit) would cause `path` to be skipped.
+    fn ignored_by_layers(&self, path: &Path) -> bool {
+        if !self.respect_ignore_files {
+            return false;
+        }
+        if self.ignore_layers.is_empty() {
+            return false;
         }
 
-        // Check file size if it's a file
-        if let Ok(metadata) = std::fs::metadata(path) {
-            if metadata.is_file() && metadata.len() > self.max_file_size {
-                return true;
+        let is_dir = self
+            .metadata_cache
+            .get(path)
+            .map(|metadata| metadata.is_dir)
+            .unwrap_or_else(|| path.is_dir());
+        let mut skip = false;
+        for (root, matcher) in &self.ignore_layers {
+            let relative = path.strip_prefix(root).unwrap_or(path);
+            let relative_str = relative.to_string_lossy().replace('\\', "/");
+            if let Some(verdict) = matcher.is_ignored(&relative_str, is_dir) {
+                skip = verdict;
             }
         }
+        skip
+    }
 
-        false
+    /// Records `metadata` (already fetched by the walker, e.g. from a
+    /// `DirEntry`) for `path`, so a later `should_skip`/
+    /// `exceeds_max_file_size` call never needs to re-stat it.
+    pub(crate) fn record_metadata(&mut self, path: PathBuf, metadata: &std::fs::Metadata) {
+        self.metadata_cache
+            .insert(path, PathMetadata::from_metadata(metadata));
+    }
+
+    /// Enables or disables deferring the file-size check out of
+    /// `should_skip` entirely (see `defer_size_check`'s doc comment).
+    pub(crate) fn set_defer_size_check(&mut self, defer: bool) {
+        self.defer_size_check = defer;
     }
 
-    /// Fast byte-level substring search.
+    /// Whether `path` exceeds `max_file_size`, consulting the metadata
+    /// cache first and falling back to a single `std::fs::metadata` call
+    /// only if `path` was never recorded via `record_metadata`. Meant to
+    /// be called right before a file's content is actually read, pairing
+    /// with `set_defer_size_check(true)`.
+    pub(crate) fn exceeds_max_file_size(&self, path: &Path) -> bool {
+        if let Some(cached) = self.metadata_cache.get(path) {
+            return cached.is_file && cached.len > self.max_file_size;
+        }
+        std::fs::metadata(path)
+            .map(|metadata| metadata.is_file() && metadata.len() > self.max_file_size)
+            .unwrap_or(false)
+    }
+
+    /// Check if path should be skipped.
+    /// A single linear pass through the Aho-Corasick automaton replaces
+    /// what used to be one byte-level search per configured pattern.
     #[inline]
-    fn bytes_contains(haystack: &[u8], needle: &[u8]) -> bool {
-        if needle.is_empty() {
+    pub(crate) fn should_skip(&self, path: &Path) -> bool {
+        let path_bytes = path.as_os_str().as_encoded_bytes();
+
+        if self.skip_automaton.is_match(path_bytes) {
             return true;
         }
-        if needle.len() > haystack.len() {
-            return false;
+
+        if self.ignored_by_layers(path) {
+            return true;
+        }
+
+        if !self.defer_size_check && self.exceeds_max_file_size(path) {
+            return true;
         }
 
-        // Simple but fast search for small needles
-        haystack.windows(needle.len()).any(|window| window == needle)
+        false
     }
 
-    /// Check if file is likely binary (UTF-16 safe heuristic).
-    /// Avoids false positives for UTF-16 text files.
+    /// Check if file is likely binary: a magic-number sniff first, then
+    /// a null-byte heuristic as a fallback for unrecognized formats.
     pub(crate) fn is_binary(&self, content: &[u8]) -> bool {
         if !self.skip_binary || content.is_empty() {
             return false;
         }
 
+        // A byte-order mark means encoded text, regardless of how many
+        // null bytes follow it.
+        if has_text_bom(content) {
+            return false;
+        }
+
+        // An exact magic-number match is definitive -- no need to fall
+        // back to the null-byte heuristic at all.
+        if has_binary_magic(content) {
+            return true;
+        }
+
         // Check first 1KB for null bytes
         let check_len = content.len().min(1024);
         let check_slice = &content[..check_len];
@@ -260,6 +858,51 @@ impl Default for PathFilter {
     }
 }
 
+/// Rare-byte prefilter for the initial full search, so the scorer only ever
+/// sees candidates that could possibly match. Typing tends to add
+/// increasingly rare bytes to the pattern, so the chosen probe byte is
+/// recomputed per full search rather than cached across keystrokes.
+#[derive(Clone, Debug)]
+pub(crate) struct Prefilter {
+    /// The pattern's least-common byte per `BYTE_FREQUENCY`, used to drive
+    /// `memchr` over candidate text. `None` for an empty pattern, which has
+    /// no byte to probe on and so can't rule anything out.
+    rare_byte: Option<u8>,
+}
+
+impl Prefilter {
+    /// Build a prefilter for `pattern`, picking its rarest byte up front.
+    pub(crate) fn new(pattern: &[u8]) -> Self {
+        Prefilter {
+            rare_byte: (!pattern.is_empty()).then(|| Self::select_rare_byte(pattern)),
+        }
+    }
+
+    /// The byte in `pattern` with the lowest background frequency per
+    /// `BYTE_FREQUENCY`, i.e. the one `memchr` can rule out text on fastest.
+    /// Ties resolve to the earliest byte. Returns `0` for an empty pattern;
+    /// callers that need to distinguish "no pattern" should check
+    /// `pattern.is_empty()` themselves, as `Prefilter::new` does.
+    pub(crate) fn select_rare_byte(pattern: &[u8]) -> u8 {
+        pattern
+            .iter()
+            .copied()
+            .min_by_key(|&byte| BYTE_FREQUENCY[byte as usize])
+            .unwrap_or(0)
+    }
+
+    /// Whether `text` could possibly match: true whenever the pattern was
+    /// empty (nothing to probe on) or `text` contains the chosen rare byte.
+    /// Never produces a false negative, since the rare byte is drawn
+    /// straight from the pattern itself.
+    pub(crate) fn survives(&self, text: &str) -> bool {
+        match self.rare_byte {
+            Some(byte) => memchr::memchr(byte, text.as_bytes()).is_some(),
+            None => true,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -286,6 +929,153 @@ mod tests {
         assert_eq!(&*cached.unwrap(), &content[..]);
     }
 
+    #[test]
+    fn test_file_content_cache_lru_evicts_least_recently_used() {
+        let mut cache = FileContentCache::new(30);
+        let a = std::path::PathBuf::from("/a.txt");
+        let b = std::path::PathBuf::from("/b.txt");
+        let c = std::path::PathBuf::from("/c.txt");
+
+        cache.insert(a.clone(), vec![0u8; 10]);
+        cache.insert(b.clone(), vec![0u8; 10]);
+        // Touch `a` so `b` becomes the least-recently-used entry.
+        cache.get(&a);
+        cache.insert(c.clone(), vec![0u8; 10]);
+
+        assert!(cache.get(&a).is_some());
+        assert!(cache.get(&b).is_none());
+        assert!(cache.get(&c).is_some());
+    }
+
+    #[test]
+    fn test_file_content_cache_lfu_evicts_fewest_hits() {
+        let mut cache = FileContentCache::with_lfu_eviction(30);
+        let a = std::path::PathBuf::from("/a.txt");
+        let b = std::path::PathBuf::from("/b.txt");
+        let c = std::path::PathBuf::from("/c.txt");
+
+        cache.insert(a.clone(), vec![0u8; 10]);
+        cache.insert(b.clone(), vec![0u8; 10]);
+        // `a` is read repeatedly; `b` is read once. Even though `b` is
+        // touched more recently than `a`, LFU should keep `a` around.
+        cache.get(&a);
+        cache.get(&a);
+        cache.get(&b);
+        cache.insert(c.clone(), vec![0u8; 10]);
+
+        assert!(cache.get(&a).is_some());
+        assert!(cache.get(&b).is_none());
+        assert!(cache.get(&c).is_some());
+    }
+
+    #[test]
+    fn test_file_content_cache_reinsert_replaces_content_without_duplicating() {
+        let mut cache = FileContentCache::new(1000);
+        let path = std::path::PathBuf::from("/test/file.txt");
+
+        cache.insert(path.clone(), b"first".to_vec());
+        cache.insert(path.clone(), b"second".to_vec());
+
+        assert_eq!(cache.stats().0, 1);
+        assert_eq!(&*cache.get(&path).unwrap(), b"second");
+    }
+
+    #[test]
+    fn test_file_content_cache_chunked_dedup_shares_chunks_across_files() {
+        let mut cache = FileContentCache::with_chunked_dedup(1_000_000);
+        let a = std::path::PathBuf::from("/a.txt");
+        let b = std::path::PathBuf::from("/b.txt");
+        // A non-repetitive shared prefix long enough to span several
+        // FastCDC chunk boundaries, plus a distinct tail per file.
+        let mut shared = Vec::with_capacity(20_000);
+        let mut state: u32 = 12345;
+        for _ in 0..20_000 {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            shared.push((state >> 24) as u8);
+        }
+        let mut content_a = shared.clone();
+        content_a.extend_from_slice(b"-a-specific-tail");
+        let mut content_b = shared;
+        content_b.extend_from_slice(b"-b-specific-tail");
+
+        cache.insert(a.clone(), content_a.clone());
+        let chunks_after_a = cache.chunk_pool_len().unwrap();
+        cache.insert(b.clone(), content_b.clone());
+        let chunks_after_b = cache.chunk_pool_len().unwrap();
+
+        // Only the differing tail's chunk should be new; the shared
+        // prefix's chunks must be reused rather than duplicated.
+        assert_eq!(chunks_after_b, chunks_after_a + 1);
+        assert_eq!(&*cache.get(&a).unwrap(), &content_a[..]);
+        assert_eq!(&*cache.get(&b).unwrap(), &content_b[..]);
+    }
+
+    #[test]
+    fn test_file_content_cache_chunked_dedup_eviction_frees_pool_chunks() {
+        let mut make_content = |seed: u32| -> Vec<u8> {
+            let mut state = seed;
+            let mut content = Vec::with_capacity(4_000);
+            for _ in 0..4_000 {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                content.push((state >> 24) as u8);
+            }
+            content
+        };
+        let a = std::path::PathBuf::from("/a.txt");
+        let b = std::path::PathBuf::from("/b.txt");
+        let content_a = make_content(42);
+        let content_b = make_content(99);
+
+        // Baseline: the pool size `b` alone settles at, with no trace of
+        // `a` ever having been inserted.
+        let mut baseline = FileContentCache::with_chunked_dedup(5_000);
+        baseline.insert(b.clone(), content_b.clone());
+        let baseline_chunks = baseline.chunk_pool_len().unwrap();
+
+        let mut cache = FileContentCache::with_chunked_dedup(5_000);
+        cache.insert(a.clone(), content_a);
+        assert!(cache.chunk_pool_len().unwrap() > 0);
+
+        // Inserting a second, equally large and unrelated file exceeds the
+        // 5,000-byte budget, forcing `a` to be evicted (LRU, and it's the
+        // only other entry) before `b` is added.
+        cache.insert(b.clone(), content_b);
+        assert!(cache.get(&a).is_none());
+
+        // The evicted file's chunks must have been released from the pool
+        // -- not left behind forever just because eviction dropped the
+        // node that referenced them -- so the pool should settle at
+        // exactly the same size as if `a` had never been inserted.
+        assert_eq!(cache.chunk_pool_len().unwrap(), baseline_chunks);
+    }
+
+    #[test]
+    fn test_file_content_cache_disk_cache_promotes_on_memory_miss() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!(
+            "fluid-grep-optimizer-disk-cache-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.txt");
+        std::fs::write(&target, b"hello from disk").unwrap();
+
+        let mut cache = FileContentCache::with_disk_cache(1000, &dir).unwrap();
+        cache.insert(target.clone(), b"hello from disk".to_vec());
+
+        // Open a second cache with an empty in-memory tier, simulating a
+        // fresh process that only has the disk tier warm.
+        let mut fresh = FileContentCache::with_disk_cache(1000, &dir).unwrap();
+        assert_eq!(fresh.stats().0, 0);
+        let fetched = fresh.get(&target);
+        assert_eq!(fetched.as_deref(), Some(b"hello from disk".as_slice()));
+        // The miss-then-promote path should have populated the memory tier.
+        assert_eq!(fresh.stats().0, 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn test_path_filter() {
         let filter = PathFilter::default_filter();
@@ -303,6 +1093,168 @@ mod tests {
         assert!(!filter.should_skip(Path::new("/project/src/main.rs")));
     }
 
+    #[test]
+    fn test_path_filter_add_skip_pattern() {
+        let mut filter = PathFilter::default_filter();
+        assert!(!filter.should_skip(Path::new("/project/coverage/report.html")));
+
+        filter.add_skip_pattern(b"coverage".to_vec());
+        assert!(filter.should_skip(Path::new("/project/coverage/report.html")));
+        // Earlier defaults still apply after the rebuild.
+        assert!(filter.should_skip(Path::new("/project/.git/config")));
+    }
+
+    #[test]
+    fn test_path_filter_set_skip_patterns_replaces_defaults() {
+        let mut filter = PathFilter::default_filter();
+        filter.set_skip_patterns([b".next".to_vec()]);
+
+        assert!(filter.should_skip(Path::new("/project/.next/cache/file")));
+        // node_modules is no longer in the skip set after replacing it.
+        assert!(!filter.should_skip(Path::new("/project/node_modules/pkg/file.js")));
+    }
+
+    #[test]
+    fn test_path_filter_respects_loaded_ignore_file() {
+        let dir = std::env::temp_dir().join(format!(
+            "fluid-grep-path-filter-ignore-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".gitignore"), "*.log\n").unwrap();
+
+        let mut filter = PathFilter::default_filter();
+        filter.load_ignore_file(&dir.join(".gitignore")).unwrap();
+
+        assert!(filter.should_skip(&dir.join("debug.log")));
+        assert!(!filter.should_skip(&dir.join("main.rs")));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_path_filter_set_respect_ignore_files_disables_loaded_layers() {
+        let dir = std::env::temp_dir().join(format!(
+            "fluid-grep-path-filter-disable-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".gitignore"), "*.log\n").unwrap();
+
+        let mut filter = PathFilter::default_filter();
+        filter.load_ignore_file(&dir.join(".gitignore")).unwrap();
+        filter.set_respect_ignore_files(false);
+
+        assert!(!filter.should_skip(&dir.join("debug.log")));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_ignored_by_layers_uses_cached_is_dir_without_a_stat_call() {
+        // Root an ignore layer at a real directory so the relative-path
+        // math works, but have the actual candidate path be one that has
+        // never existed on disk -- a live `Path::is_dir` call would return
+        // `false` for it, so a dir-only rule only matching here proves the
+        // cached `is_dir` was consulted instead.
+        let dir = std::env::temp_dir().join(format!(
+            "fluid-grep-ignored-by-layers-cache-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".gitignore"), "logs/\n").unwrap();
+
+        let mut filter = PathFilter::default_filter();
+        filter.load_ignore_file(&dir.join(".gitignore")).unwrap();
+
+        let phantom_dir = dir.join("logs");
+        let real_file = dir.join("stand-in.txt");
+        std::fs::write(&real_file, b"x").unwrap();
+        let mut metadata = std::fs::metadata(&real_file).unwrap();
+        // Borrow a real `Metadata` but only trust its `is_dir` bit here --
+        // everything else about `phantom_dir` is fabricated for the test.
+        assert!(!metadata.is_dir());
+        drop(metadata);
+        metadata = std::fs::metadata(&dir).unwrap();
+        assert!(metadata.is_dir());
+
+        filter.record_metadata(phantom_dir.clone(), &metadata);
+        assert!(filter.should_skip(&phantom_dir));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_should_skip_uses_cached_metadata_without_a_stat_call() {
+        let mut filter = PathFilter::default_filter();
+        // A path that has never existed on disk: a live `std::fs::metadata`
+        // call would fail, so a `true` result here can only have come from
+        // the cached entry.
+        let path = std::path::PathBuf::from("/definitely/does/not/exist.rs");
+        filter.max_file_size = 10;
+
+        let dir = std::env::temp_dir().join(format!(
+            "fluid-grep-metadata-cache-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let real_file = dir.join("stand-in.txt");
+        std::fs::write(&real_file, vec![0u8; 20]).unwrap();
+        let metadata = std::fs::metadata(&real_file).unwrap();
+
+        filter.record_metadata(path.clone(), &metadata);
+        assert!(filter.should_skip(&path));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_defer_size_check_skips_size_enforcement_until_asked() {
+        let dir = std::env::temp_dir().join(format!(
+            "fluid-grep-defer-size-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let big_file = dir.join("big.txt");
+        std::fs::write(&big_file, vec![0u8; 100]).unwrap();
+
+        let mut filter = PathFilter::default_filter();
+        filter.max_file_size = 10;
+        filter.set_defer_size_check(true);
+
+        assert!(!filter.should_skip(&big_file));
+        assert!(filter.exceeds_max_file_size(&big_file));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_aho_corasick_matches_any_configured_pattern() {
+        let automaton = AhoCorasick::build(&[b"foo".to_vec(), b"bar".to_vec()]);
+        assert!(automaton.is_match(b"a foo b"));
+        assert!(automaton.is_match(b"xxbarxx"));
+        assert!(!automaton.is_match(b"neither here"));
+    }
+
+    #[test]
+    fn test_aho_corasick_empty_pattern_set_never_matches() {
+        let automaton = AhoCorasick::build(&[]);
+        assert!(!automaton.is_match(b"anything at all"));
+    }
+
+    #[test]
+    fn test_aho_corasick_overlapping_suffix_patterns() {
+        // "she" and "he" share the suffix "he", exercising failure links.
+        let automaton = AhoCorasick::build(&[b"she".to_vec(), b"he".to_vec()]);
+        assert!(automaton.is_match(b"ushers"));
+        assert!(!automaton.is_match(b"xyz"));
+    }
+
     #[test]
     fn test_binary_detection() {
         let filter = PathFilter::default_filter();
@@ -322,4 +1274,67 @@ mod tests {
         ];
         assert!(!filter.is_binary(&utf16));
     }
+
+    #[test]
+    fn test_binary_detection_recognizes_magic_numbers() {
+        let filter = PathFilter::default_filter();
+
+        assert!(filter.is_binary(b"\x7fELF\x02\x01\x01\x00"));
+        assert!(filter.is_binary(b"MZ\x90\x00\x03\x00\x00\x00"));
+        assert!(filter.is_binary(b"%PDF-1.7\n"));
+        assert!(filter.is_binary(b"PK\x03\x04\x14\x00\x00\x00"));
+        assert!(filter.is_binary(b"\x1f\x8b\x08\x00\x00\x00\x00\x00"));
+        assert!(filter.is_binary(b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0d"));
+        assert!(filter.is_binary(b"\xff\xd8\xff\xe0\x00\x10JFIF"));
+        assert!(filter.is_binary(b"GIF89a\x01\x00\x01\x00"));
+    }
+
+    #[test]
+    fn test_binary_detection_respects_byte_order_marks() {
+        let filter = PathFilter::default_filter();
+
+        // UTF-8 BOM followed by plain text.
+        assert!(!filter.is_binary(b"\xef\xbb\xbftext after the BOM"));
+
+        // UTF-16LE BOM followed by nulls that would otherwise look
+        // ambiguous to the fallback heuristic.
+        let mut utf16le_bom = vec![0xff, 0xfe];
+        utf16le_bom.extend_from_slice(b"h\0i\0");
+        assert!(!filter.is_binary(&utf16le_bom));
+
+        // UTF-32LE BOM, checked ahead of the shorter UTF-16LE BOM prefix.
+        let mut utf32le_bom = vec![0xff, 0xfe, 0x00, 0x00];
+        utf32le_bom.extend_from_slice(b"h\0\0\0i\0\0\0");
+        assert!(!filter.is_binary(&utf32le_bom));
+    }
+
+    #[test]
+    fn test_prefilter_selects_rarest_byte() {
+        // 'z' is far rarer than 'e'/'t'/'a' per BYTE_FREQUENCY.
+        assert_eq!(Prefilter::select_rare_byte(b"eatz"), b'z');
+    }
+
+    #[test]
+    fn test_prefilter_ties_pick_first_byte() {
+        // Two untuned bytes share the default frequency, so the first wins.
+        assert_eq!(Prefilter::select_rare_byte(&[0x01, 0x02]), 0x01);
+    }
+
+    #[test]
+    fn test_prefilter_survives_true_when_rare_byte_present() {
+        let prefilter = Prefilter::new(b"fzgrep");
+        assert!(prefilter.survives("a quick fuzzy search"));
+    }
+
+    #[test]
+    fn test_prefilter_survives_false_when_rare_byte_absent() {
+        let prefilter = Prefilter::new(b"fzgrep");
+        assert!(!prefilter.survives("no rare bytes in this line"));
+    }
+
+    #[test]
+    fn test_prefilter_empty_pattern_always_survives() {
+        let prefilter = Prefilter::new(b"");
+        assert!(prefilter.survives("anything at all"));
+    }
 }
@@ -0,0 +1,186 @@
+/*!
+Regex query mode backed by a lazy (hybrid) DFA.
+
+Fuzzy subsequence scoring is the right default for "type a few letters and
+narrow down", but power users sometimes know exactly what they want --
+`\bfn\s+\w+`, anchors, alternation. `QueryKind::Regex` opts a pattern into
+that mode instead of the fuzzy scorer. Patterns are compiled with
+`regex-automata`'s hybrid DFA: lazy construction keeps the up-front cost low
+even though the full DFA for a complex pattern can be large, and
+`RegexMatcher` caches the compiled automaton by pattern string so repeated
+searches against the same pattern (the common case while typing) don't pay
+to recompile it.
+*/
+
+use std::collections::HashMap;
+
+use regex_automata::hybrid::regex::{Cache, Regex};
+
+use crate::regex_prefilter::{parse_pattern, RegexPrefilter};
+
+/// Distinguishes a fuzzy/subsequence query from one that should be
+/// compiled and run as a regex against candidate text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum QueryKind {
+    /// Score with the fzf-style fuzzy subsequence matcher.
+    Fuzzy,
+    /// Compile as a regex and run through the hybrid DFA.
+    Regex,
+}
+
+/// Runs regex-mode queries through a cached hybrid (lazy) DFA.
+///
+/// Compiling a pattern into a DFA is the expensive part; this caches the
+/// compiled `Regex` (and its scratch `Cache`) by the pattern's source
+/// string, so scoring the same pattern against many candidates -- or
+/// across consecutive keystrokes that reuse the old pattern -- only pays
+/// the compile cost once.
+pub(crate) struct RegexMatcher {
+    compiled: HashMap<String, (Regex, Cache, RegexPrefilter)>,
+}
+
+impl RegexMatcher {
+    /// Create an empty matcher with no compiled patterns cached yet.
+    pub(crate) fn new() -> Self {
+        RegexMatcher {
+            compiled: HashMap::new(),
+        }
+    }
+
+    /// Compiles `pattern` if it isn't already cached, returning its entry.
+    /// Returns `None` for an invalid regex rather than panicking -- an
+    /// in-progress keystroke (e.g. `\bfn(` with an unclosed group) is
+    /// expected to be unparsable some of the time.
+    fn entry(&mut self, pattern: &str) -> Option<&mut (Regex, Cache, RegexPrefilter)> {
+        if !self.compiled.contains_key(pattern) {
+            let regex = Regex::new(pattern).ok()?;
+            let cache = regex.create_cache();
+            let prefilter = RegexPrefilter::build(&parse_pattern(pattern));
+            self.compiled
+                .insert(pattern.to_string(), (regex, cache, prefilter));
+        }
+        self.compiled.get_mut(pattern)
+    }
+
+    /// Scores `text` against `pattern` via a leftmost search: 1000 for a
+    /// full-string match, otherwise scaled by how much of `text` the match
+    /// spans and how early it starts, so a short match near the front of a
+    /// long line still ranks above one buried in the middle. Returns 0 for
+    /// no match (including an unparsable pattern), so this can be used
+    /// anywhere `incremental_score`/`smith_waterman_score` are.
+    pub(crate) fn score(&mut self, pattern: &str, text: &str) -> u32 {
+        let Some((regex, cache, prefilter)) = self.entry(pattern) else {
+            return 0;
+        };
+
+        // If the pattern has a provably required literal and it doesn't
+        // occur anywhere in `text`, `text` cannot possibly match -- skip
+        // the hybrid DFA search entirely.
+        if matches!(prefilter.candidate_offsets(text.as_bytes()), Some(offsets) if offsets.is_empty())
+        {
+            return 0;
+        }
+
+        let Some(mat) = regex.find(cache, text) else {
+            return 0;
+        };
+
+        if mat.start() == 0 && mat.end() == text.len() {
+            return 1000;
+        }
+
+        let text_len = text.len().max(1) as f32;
+        let coverage = (mat.end() - mat.start()) as f32 / text_len;
+        let position_bonus = 1.0 - (mat.start() as f32 / text_len);
+        ((coverage * 0.7 + position_bonus * 0.3) * 1000.0) as u32
+    }
+
+    /// Number of distinct patterns currently compiled and cached.
+    pub(crate) fn len(&self) -> usize {
+        self.compiled.len()
+    }
+
+    /// Drop every cached compiled pattern.
+    pub(crate) fn clear(&mut self) {
+        self.compiled.clear();
+    }
+}
+
+impl Default for RegexMatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_string_match_scores_max() {
+        let mut matcher = RegexMatcher::new();
+        assert_eq!(matcher.score(r"fn\s+\w+", "fn main"), 1000);
+    }
+
+    #[test]
+    fn test_partial_match_scores_below_max() {
+        let mut matcher = RegexMatcher::new();
+        let score = matcher.score(r"fn\s+\w+", "  fn main() {}");
+        assert!(score > 0 && score < 1000);
+    }
+
+    #[test]
+    fn test_no_match_scores_zero() {
+        let mut matcher = RegexMatcher::new();
+        assert_eq!(matcher.score(r"^struct\b", "fn main() {}"), 0);
+    }
+
+    #[test]
+    fn test_literal_prefilter_skips_dfa_search_when_literal_absent() {
+        let mut matcher = RegexMatcher::new();
+        // "struct" is a provably required literal; text missing it entirely
+        // should score 0 without needing the DFA to run at all.
+        assert_eq!(matcher.score(r"struct\s+\w+", "fn main() {}"), 0);
+        // A text that does contain the literal must still be scored normally.
+        assert!(matcher.score(r"struct\s+\w+", "struct Foo;") > 0);
+    }
+
+    #[test]
+    fn test_invalid_pattern_scores_zero_instead_of_panicking() {
+        let mut matcher = RegexMatcher::new();
+        assert_eq!(matcher.score(r"fn(", "fn main"), 0);
+    }
+
+    #[test]
+    fn test_compiled_pattern_is_cached() {
+        let mut matcher = RegexMatcher::new();
+        matcher.score(r"\bfn\b", "fn main");
+        assert_eq!(matcher.len(), 1);
+
+        matcher.score(r"\bfn\b", "another candidate");
+        assert_eq!(matcher.len(), 1); // reused, not recompiled
+
+        matcher.score(r"\bstruct\b", "struct Foo;");
+        assert_eq!(matcher.len(), 2);
+    }
+
+    #[test]
+    fn test_clear_drops_compiled_patterns() {
+        let mut matcher = RegexMatcher::new();
+        matcher.score(r"fn", "fn main");
+        assert_eq!(matcher.len(), 1);
+
+        matcher.clear();
+        assert_eq!(matcher.len(), 0);
+    }
+
+    #[test]
+    fn test_earlier_match_scores_higher_than_later_one() {
+        let mut matcher = RegexMatcher::new();
+        // Same length and same match span either way, so only the match's
+        // position differs.
+        let early = matcher.score("cat", "catxxxxxxxxxxxxxxxxxxxxxxxxxxxx");
+        let late = matcher.score("cat", "xxxxxxxxxxxxxxxxxxxxxxxxxxxxcat");
+        assert!(early > late);
+    }
+}
@@ -0,0 +1,500 @@
+/*!
+Literal prefilter for regex queries.
+
+Running a full regex engine on every line is wasteful when a pattern can
+only match text that contains one of a small set of literal byte strings.
+This module extracts that "literal requirement" from a pattern's structure
+and uses the existing `SimdMatcher`/`SimdMultiMatcher` scanners to find
+candidate lines first, so the (external) regex engine only has to run on
+lines/windows where a required literal actually occurs. When no literal can
+be proven required -- e.g. the pattern starts with `.*` or has an
+alternation branch with no literal of its own -- the prefilter transparently
+reports "no filtering possible" so callers fall back to checking everything,
+and correctness is never compromised.
+*/
+
+use crate::file_scan::LineIndex;
+use crate::simd::{SimdMatcher, SimdMultiMatcher};
+
+/// Minimal structural view of a compiled pattern, detailed enough to prove
+/// which literal byte strings are *required* for any match. This module
+/// doesn't execute the pattern itself -- it only reasons about what must be
+/// present in the text for the real regex engine's work to be worth
+/// attempting.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum PatternAst {
+    /// A run of literal bytes that must match exactly.
+    Literal(Vec<u8>),
+    /// Sub-patterns that must all match in sequence.
+    Concat(Vec<PatternAst>),
+    /// Any one of several alternatives.
+    Alternation(Vec<PatternAst>),
+    /// Zero or more repetitions of a sub-pattern (`*`), which can never be
+    /// relied on to appear at all.
+    Star(Box<PatternAst>),
+    /// Matches any single byte/char; contributes no literal information.
+    AnyChar,
+}
+
+/// Parses the source text of a regex pattern into the conservative
+/// `PatternAst` view this module reasons over. This is deliberately not a
+/// full regex parser: anything it doesn't fully understand (groups,
+/// character classes, backreferences, ...) is folded into `AnyChar` rather
+/// than guessed at, so `extract_requirement` can never be handed a literal
+/// that isn't actually required -- worst case, a construct we don't model
+/// just loses some filtering opportunity instead of risking one.
+pub(crate) fn parse_pattern(pattern: &str) -> PatternAst {
+    // Top-level alternation only; `|` inside a group or class is handled by
+    // `parse_concat`'s own depth tracking, so this only ever splits on a
+    // `|` that's actually at depth 0.
+    let branches = split_top_level_alternation(pattern);
+    if branches.len() > 1 {
+        PatternAst::Alternation(branches.iter().map(|b| parse_concat(b)).collect())
+    } else {
+        parse_concat(pattern)
+    }
+}
+
+/// Splits `pattern` on every unescaped `|` that isn't nested inside a
+/// group (`(...)`) or character class (`[...]`).
+fn split_top_level_alternation(pattern: &str) -> Vec<&str> {
+    let bytes = pattern.as_bytes();
+    let mut branches = Vec::new();
+    let mut depth = 0i32;
+    let mut in_class = false;
+    let mut start = 0usize;
+    let mut i = 0usize;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1, // skip the escaped byte too
+            b'[' if !in_class => in_class = true,
+            b']' if in_class => in_class = false,
+            b'(' if !in_class => depth += 1,
+            b')' if !in_class => depth -= 1,
+            b'|' if !in_class && depth == 0 => {
+                branches.push(&pattern[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    branches.push(&pattern[start..]);
+    branches
+}
+
+/// Recognized escapes whose literal byte value is the escaped character
+/// itself (either a regex metacharacter escaped to mean itself, or a
+/// common whitespace escape that's a literal byte at match time).
+fn literal_escape_byte(ch: char) -> Option<u8> {
+    match ch {
+        '.' | '^' | '$' | '|' | '(' | ')' | '[' | ']' | '{' | '}' | '*' | '+' | '?' | '\\' => {
+            Some(ch as u8)
+        }
+        'n' => Some(b'\n'),
+        't' => Some(b'\t'),
+        'r' => Some(b'\r'),
+        _ => None,
+    }
+}
+
+/// Parses one alternation-free branch into a `Concat` of literal runs and
+/// `AnyChar`/`Star` placeholders for everything else, with quantifiers
+/// (`*`, `+`, `?`, `{m,n}`) retroactively marking the atom they apply to as
+/// a `Star` -- so e.g. `ab*` correctly requires only `"a"`, not `"ab"`.
+fn parse_concat(segment: &str) -> PatternAst {
+    let mut parts: Vec<PatternAst> = Vec::new();
+    let mut literal = Vec::new();
+    let chars: Vec<char> = segment.chars().collect();
+    let mut i = 0usize;
+
+    let flush = |literal: &mut Vec<u8>, parts: &mut Vec<PatternAst>| {
+        if !literal.is_empty() {
+            parts.push(PatternAst::Literal(std::mem::take(literal)));
+        }
+    };
+
+    // Marks whatever was just parsed (the last literal byte, or the last
+    // pushed part) as optional/repeated rather than required.
+    let make_previous_starred = |literal: &mut Vec<u8>, parts: &mut Vec<PatternAst>| {
+        if let Some(byte) = literal.pop() {
+            flush(literal, parts);
+            parts.push(PatternAst::Star(Box::new(PatternAst::Literal(vec![byte]))));
+        } else if let Some(last) = parts.pop() {
+            parts.push(PatternAst::Star(Box::new(last)));
+        }
+    };
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                if let Some(next) = chars.get(i + 1) {
+                    if let Some(byte) = literal_escape_byte(*next) {
+                        literal.push(byte);
+                    } else {
+                        // An unrecognized escape (`\s`, `\d`, `\w`, `\b`, ...)
+                        // is a class or assertion, not a literal byte.
+                        flush(&mut literal, &mut parts);
+                        parts.push(PatternAst::AnyChar);
+                    }
+                    i += 2;
+                    // The argument of `\x41`, `\u{263A}`, `\pL` or `\p{Greek}`
+                    // belongs to the escape, not to the literal text after it.
+                    if matches!(*next, 'x' | 'u' | 'U' | 'p' | 'P') {
+                        if chars.get(i) == Some(&'{') {
+                            while i < chars.len() && chars[i] != '}' {
+                                i += 1;
+                            }
+                            i += 1; // past the closing '}'
+                        } else if matches!(*next, 'p' | 'P') {
+                            i += 1;
+                        } else {
+                            while i < chars.len() && chars[i].is_ascii_hexdigit() {
+                                i += 1;
+                            }
+                        }
+                    }
+                } else {
+                    literal.push(b'\\');
+                    i += 1;
+                }
+            }
+            '.' => {
+                flush(&mut literal, &mut parts);
+                parts.push(PatternAst::AnyChar);
+                i += 1;
+            }
+            '^' | '$' => {
+                // Zero-width assertions: contribute nothing either way.
+                i += 1;
+            }
+            '[' => {
+                flush(&mut literal, &mut parts);
+                parts.push(PatternAst::AnyChar);
+                i += 1;
+                if chars.get(i) == Some(&'^') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i] != ']' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1; // past the closing ']'
+            }
+            '(' => {
+                // Groups aren't parsed recursively (see `parse_pattern`);
+                // conservatively treat the whole group as opaque.
+                flush(&mut literal, &mut parts);
+                parts.push(PatternAst::AnyChar);
+                let group_start = i + 1;
+                let mut depth = 1;
+                i += 1;
+                while i < chars.len() && depth > 0 {
+                    match chars[i] {
+                        '\\' => i += 1,
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                // A bare flag group such as `(?i)` or `(?x)` changes how
+                // everything after it matches, so nothing after it is
+                // taken as a required literal.
+                let group = &chars[group_start..i.saturating_sub(1).max(group_start)];
+                if group.first() == Some(&'?') && !group.contains(&':') {
+                    break;
+                }
+            }
+            '*' | '+' | '?' => {
+                make_previous_starred(&mut literal, &mut parts);
+                i += 1;
+            }
+            '{' => {
+                make_previous_starred(&mut literal, &mut parts);
+                while i < chars.len() && chars[i] != '}' {
+                    i += 1;
+                }
+                i += 1; // past the closing '}'
+            }
+            ch => {
+                literal.extend(ch.to_string().as_bytes());
+                i += 1;
+            }
+        }
+    }
+
+    flush(&mut literal, &mut parts);
+
+    match parts.len() {
+        0 => PatternAst::Literal(Vec::new()),
+        1 => parts.into_iter().next().unwrap(),
+        _ => PatternAst::Concat(parts),
+    }
+}
+
+/// Describes which literal(s), if any, are provably required for a pattern
+/// to match. `Required(literals)` means at least one of the given byte
+/// strings must occur in any matching text, so scanning for them first can
+/// only rule out non-matches, never hide a real one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum LiteralRequirement {
+    /// At least one of these literals must appear in any match.
+    Required(Vec<Vec<u8>>),
+    /// No literal could be proven necessary; the caller must fall back to
+    /// running the full engine on everything.
+    None,
+}
+
+/// Recursively proves the `LiteralRequirement` for `ast`.
+fn extract_requirement(ast: &PatternAst) -> LiteralRequirement {
+    match ast {
+        PatternAst::Literal(bytes) => {
+            if bytes.is_empty() {
+                LiteralRequirement::None
+            } else {
+                LiteralRequirement::Required(vec![bytes.clone()])
+            }
+        }
+        PatternAst::AnyChar | PatternAst::Star(_) => LiteralRequirement::None,
+        PatternAst::Concat(children) => {
+            // All children must match, so a required literal from *any one*
+            // of them is a valid (if not maximally selective) requirement
+            // for the whole pattern. Prefer the longest literal, since
+            // longer literals filter out more candidates.
+            let mut best: Option<Vec<Vec<u8>>> = None;
+            for child in children {
+                if let LiteralRequirement::Required(lits) = extract_requirement(child) {
+                    let child_min_len = lits.iter().map(Vec::len).min().unwrap_or(0);
+                    let best_min_len = best
+                        .as_ref()
+                        .map(|b| b.iter().map(Vec::len).min().unwrap_or(0))
+                        .unwrap_or(0);
+                    if best.is_none() || child_min_len > best_min_len {
+                        best = Some(lits);
+                    }
+                }
+            }
+            best.map(LiteralRequirement::Required)
+                .unwrap_or(LiteralRequirement::None)
+        }
+        PatternAst::Alternation(children) => {
+            // Only one branch needs to match, so we can only claim a
+            // literal is required if *every* branch has one of its own --
+            // the requirement set is then the union of all of them, since
+            // whichever branch matches, one of its literals is present.
+            let mut all_lits = Vec::new();
+            for child in children {
+                match extract_requirement(child) {
+                    LiteralRequirement::Required(lits) => all_lits.extend(lits),
+                    LiteralRequirement::None => return LiteralRequirement::None,
+                }
+            }
+            if all_lits.is_empty() {
+                LiteralRequirement::None
+            } else {
+                LiteralRequirement::Required(all_lits)
+            }
+        }
+    }
+}
+
+/// A prefilter built from a pattern's literal requirements, backed by the
+/// same fast scanners `SimdMatcher`/`SimdMultiMatcher` use elsewhere in
+/// this crate.
+pub(crate) enum RegexPrefilter {
+    /// A single required literal; scanned with `SimdMatcher::find_all`.
+    Single(Vec<u8>),
+    /// Several alternative required literals; scanned once with
+    /// `SimdMultiMatcher` and the results unioned.
+    Multi(SimdMultiMatcher),
+    /// No literal could be proven required: every candidate must be
+    /// checked by the full engine.
+    Unfiltered,
+}
+
+impl RegexPrefilter {
+    /// Builds the most selective prefilter provable from `ast`.
+    pub(crate) fn build(ast: &PatternAst) -> Self {
+        match extract_requirement(ast) {
+            LiteralRequirement::None => RegexPrefilter::Unfiltered,
+            LiteralRequirement::Required(mut literals) => {
+                literals.sort();
+                literals.dedup();
+                match literals.len() {
+                    1 => RegexPrefilter::Single(literals.into_iter().next().unwrap()),
+                    _ => {
+                        let refs: Vec<&[u8]> = literals.iter().map(Vec::as_slice).collect();
+                        RegexPrefilter::Multi(SimdMultiMatcher::new(&refs))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds the byte offsets in `text` where a required literal begins,
+    /// deduplicated and sorted. Returns `None` when this prefilter can't
+    /// narrow anything down, meaning the caller must check every candidate.
+    pub(crate) fn candidate_offsets(&self, text: &[u8]) -> Option<Vec<usize>> {
+        match self {
+            RegexPrefilter::Unfiltered => None,
+            RegexPrefilter::Single(literal) => Some(SimdMatcher::find_all(literal, text)),
+            RegexPrefilter::Multi(matcher) => {
+                let mut offsets: Vec<usize> =
+                    matcher.find_all(text).into_iter().flatten().collect();
+                offsets.sort_unstable();
+                offsets.dedup();
+                Some(offsets)
+            }
+        }
+    }
+}
+
+/// Runs `verify` (the real regex engine) only on the lines of `content`
+/// that contain a literal-prefilter candidate, or on every line if
+/// `prefilter` couldn't prove any literal required. Returns the 1-based
+/// line numbers where `verify` returned true.
+pub(crate) fn filter_lines(
+    prefilter: &RegexPrefilter,
+    content: &[u8],
+    verify: impl Fn(&[u8]) -> bool,
+) -> Vec<usize> {
+    let line_index = LineIndex::build(content);
+    let all_lines: Vec<(usize, &[u8])> = line_index.iter_lines(content).collect();
+
+    match prefilter.candidate_offsets(content) {
+        Some(offsets) => {
+            let mut candidate_line_nos: Vec<usize> = offsets
+                .iter()
+                .map(|&offset| line_index.locate(content, offset).0)
+                .collect();
+            candidate_line_nos.sort_unstable();
+            candidate_line_nos.dedup();
+
+            candidate_line_nos
+                .into_iter()
+                .filter(|&line_no| {
+                    all_lines
+                        .get(line_no - 1)
+                        .is_some_and(|&(_, text)| verify(text))
+                })
+                .collect()
+        }
+        None => all_lines
+            .into_iter()
+            .filter_map(|(line_no, text)| verify(text).then_some(line_no))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(s: &str) -> PatternAst {
+        PatternAst::Literal(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_requirement_single_literal() {
+        let ast = lit("fn ");
+        let prefilter = RegexPrefilter::build(&ast);
+        assert!(matches!(prefilter, RegexPrefilter::Single(ref l) if l == b"fn "));
+    }
+
+    #[test]
+    fn test_requirement_unbounded_star_has_no_filter() {
+        let ast = PatternAst::Star(Box::new(PatternAst::AnyChar));
+        let prefilter = RegexPrefilter::build(&ast);
+        assert!(matches!(prefilter, RegexPrefilter::Unfiltered));
+    }
+
+    #[test]
+    fn test_requirement_concat_picks_a_required_literal() {
+        // `.*foo` -- the `.*` contributes nothing, but `foo` is required.
+        let ast = PatternAst::Concat(vec![
+            PatternAst::Star(Box::new(PatternAst::AnyChar)),
+            lit("foo"),
+        ]);
+        let prefilter = RegexPrefilter::build(&ast);
+        assert!(matches!(prefilter, RegexPrefilter::Single(ref l) if l == b"foo"));
+    }
+
+    #[test]
+    fn test_requirement_concat_prefers_longer_literal() {
+        let ast = PatternAst::Concat(vec![lit("a"), lit("longerliteral")]);
+        let prefilter = RegexPrefilter::build(&ast);
+        assert!(matches!(prefilter, RegexPrefilter::Single(ref l) if l == b"longerliteral"));
+    }
+
+    #[test]
+    fn test_requirement_alternation_all_branches_literal() {
+        let ast = PatternAst::Alternation(vec![lit("cat"), lit("dog")]);
+        let prefilter = RegexPrefilter::build(&ast);
+        assert!(matches!(prefilter, RegexPrefilter::Multi(_)));
+    }
+
+    #[test]
+    fn test_requirement_alternation_with_unconstrained_branch_has_no_filter() {
+        // `cat|.*` -- the second branch can match without any literal, so
+        // nothing can be proven required for the whole alternation.
+        let ast = PatternAst::Alternation(vec![
+            lit("cat"),
+            PatternAst::Star(Box::new(PatternAst::AnyChar)),
+        ]);
+        let prefilter = RegexPrefilter::build(&ast);
+        assert!(matches!(prefilter, RegexPrefilter::Unfiltered));
+    }
+
+    #[test]
+    fn test_candidate_offsets_single_literal() {
+        let prefilter = RegexPrefilter::build(&lit("fn"));
+        let offsets = prefilter
+            .candidate_offsets(b"fn main() { fn nested() {} }")
+            .unwrap();
+        assert_eq!(offsets, vec![0, 12]);
+    }
+
+    #[test]
+    fn test_candidate_offsets_multi_literal_union_and_dedup() {
+        let ast = PatternAst::Alternation(vec![lit("cat"), lit("dog")]);
+        let prefilter = RegexPrefilter::build(&ast);
+        let offsets = prefilter
+            .candidate_offsets(b"the cat met the dog near the cat")
+            .unwrap();
+        assert_eq!(offsets, vec![4, 16, 29]);
+    }
+
+    #[test]
+    fn test_candidate_offsets_unfiltered_returns_none() {
+        let prefilter = RegexPrefilter::Unfiltered;
+        assert!(prefilter.candidate_offsets(b"anything").is_none());
+    }
+
+    #[test]
+    fn test_filter_lines_only_verifies_candidate_lines() {
+        let content = b"no match here\nfn main() {}\nanother miss\nfn nested() {}\n";
+        let prefilter = RegexPrefilter::build(&lit("fn "));
+
+        let verified_lines = std::cell::RefCell::new(Vec::new());
+        let matches = filter_lines(&prefilter, content, |line| {
+            verified_lines.borrow_mut().push(line.to_vec());
+            line.starts_with(b"fn ")
+        });
+
+        assert_eq!(matches, vec![2, 4]);
+        // Only the two candidate lines were ever handed to `verify`.
+        assert_eq!(verified_lines.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_filter_lines_falls_back_to_checking_every_line() {
+        let content = b"alpha\nbeta\ngamma\n";
+        let prefilter = RegexPrefilter::Unfiltered;
+
+        let matches = filter_lines(&prefilter, content, |line| line.contains(&b'a'));
+        assert_eq!(matches, vec![1, 2, 3]);
+    }
+}
@@ -16,9 +16,15 @@ use std::path::{Path, PathBuf};
 use std::fs;
 use std::io::Write;
 use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Current config schema version. Bump this and add a `migrate_vN_to_vN1`
+/// function below whenever the on-disk schema changes.
+const CONFIG_VERSION: u32 = 2;
 
 /// Search mode enum to prevent user typos.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub(crate) enum SearchMode {
     Original,
     Fluid,
@@ -49,9 +55,183 @@ impl Default for SearchMode {
     }
 }
 
+/// Describes how a config value type should be presented in docs and TOML
+/// comments. Mirrors the doc-hint approach rustfmt's `Config` derive uses.
+pub(crate) trait ConfigType {
+    /// A short hint describing valid values, e.g. `"<boolean>"` or a
+    /// pipe-separated list of enum variants like `"fluid|original"`.
+    fn doc_hint() -> String;
+}
+
+impl ConfigType for bool {
+    fn doc_hint() -> String {
+        "<boolean>".to_string()
+    }
+}
+
+impl ConfigType for u32 {
+    fn doc_hint() -> String {
+        "<unsigned integer>".to_string()
+    }
+}
+
+impl ConfigType for u64 {
+    fn doc_hint() -> String {
+        "<unsigned integer>".to_string()
+    }
+}
+
+impl ConfigType for usize {
+    fn doc_hint() -> String {
+        "<unsigned integer>".to_string()
+    }
+}
+
+impl ConfigType for f32 {
+    fn doc_hint() -> String {
+        "<float>".to_string()
+    }
+}
+
+impl ConfigType for Option<usize> {
+    fn doc_hint() -> String {
+        "<unsigned integer>|none".to_string()
+    }
+}
+
+impl ConfigType for SearchMode {
+    fn doc_hint() -> String {
+        "fluid|original".to_string()
+    }
+}
+
+/// One entry in the config documentation registry: key, value hint, default,
+/// optional valid range, and a one-line description.
+pub(crate) struct ConfigFieldDoc {
+    pub key: &'static str,
+    pub hint: String,
+    pub default: String,
+    pub range: Option<&'static str>,
+    pub description: &'static str,
+}
+
+/// Registry describing every `RipgrepConfig` field. Drives both the annotated
+/// TOML emitted by `to_toml` and the `--print-config`/`--config-docs` dump, so
+/// the two can never drift out of sync.
+pub(crate) fn config_field_docs() -> Vec<ConfigFieldDoc> {
+    let defaults = RipgrepConfig::default();
+    vec![
+        ConfigFieldDoc {
+            key: "version",
+            hint: u32::doc_hint(),
+            default: defaults.version.to_string(),
+            range: None,
+            description: "Config schema version, bumped when migrations run",
+        },
+        ConfigFieldDoc {
+            key: "default_mode",
+            hint: SearchMode::doc_hint(),
+            default: defaults.default_mode.as_str().to_string(),
+            range: None,
+            description: "Default search mode",
+        },
+        ConfigFieldDoc {
+            key: "fluid_disabled",
+            hint: bool::doc_hint(),
+            default: defaults.fluid_disabled.to_string(),
+            range: None,
+            description: "Disable fluid mode permanently",
+        },
+        ConfigFieldDoc {
+            key: "fuzzy_threshold",
+            hint: f32::doc_hint(),
+            default: defaults.fuzzy_threshold.to_string(),
+            range: Some("0.0-1.0"),
+            description: "Fuzzy matching threshold",
+        },
+        ConfigFieldDoc {
+            key: "max_edit_distance",
+            hint: Option::<usize>::doc_hint(),
+            default: "none".to_string(),
+            range: Some("0-100"),
+            description: "Maximum edit distance for typo tolerance",
+        },
+        ConfigFieldDoc {
+            key: "heuristic_disabled",
+            hint: bool::doc_hint(),
+            default: defaults.heuristic_disabled.to_string(),
+            range: None,
+            description: "Disable heuristic scoring (use only fuzzy matching)",
+        },
+        ConfigFieldDoc {
+            key: "word_boundary_bonus",
+            hint: f32::doc_hint(),
+            default: defaults.word_boundary_bonus.to_string(),
+            range: Some("0.0-1.0"),
+            description: "Word boundary bonus weight",
+        },
+        ConfigFieldDoc {
+            key: "consecutive_match_bonus",
+            hint: f32::doc_hint(),
+            default: defaults.consecutive_match_bonus.to_string(),
+            range: None,
+            description: "Consecutive match bonus weight",
+        },
+        ConfigFieldDoc {
+            key: "max_results",
+            hint: usize::doc_hint(),
+            default: defaults.max_results.to_string(),
+            range: Some("0 = unlimited"),
+            description: "Maximum results per search",
+        },
+        ConfigFieldDoc {
+            key: "enable_incremental",
+            hint: bool::doc_hint(),
+            default: defaults.enable_incremental.to_string(),
+            range: None,
+            description: "Enable incremental caching",
+        },
+        ConfigFieldDoc {
+            key: "cache_size_mb",
+            hint: usize::doc_hint(),
+            default: defaults.cache_size_mb.to_string(),
+            range: None,
+            description: "File content cache size in MB",
+        },
+        ConfigFieldDoc {
+            key: "min_pattern_length",
+            hint: usize::doc_hint(),
+            default: defaults.min_pattern_length.to_string(),
+            range: None,
+            description: "Minimum pattern length for search",
+        },
+        ConfigFieldDoc {
+            key: "timeout_ms",
+            hint: u64::doc_hint(),
+            default: defaults.timeout_ms.to_string(),
+            range: Some("0 = no timeout"),
+            description: "Search timeout in milliseconds",
+        },
+    ]
+}
+
+/// Records which config files, if any, contributed to the effective
+/// configuration returned by `RipgrepConfig::load_with_sources`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ConfigSources {
+    /// The global config file that was loaded: an explicit
+    /// `FLUIDGREP_CONFIG_PATH`/`RIPGREP_CONFIG_PATH` override, or the
+    /// XDG/APPDATA default. `None` if no global config file exists on disk.
+    pub global: Option<PathBuf>,
+    /// A project-local `.fluidgreprc`, found by walking up from the current
+    /// directory, that was merged on top of the global config.
+    pub project: Option<PathBuf>,
+}
+
 /// Represents ripgrep's persistent configuration.
 /// All values are validated and have sensible defaults.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub(crate) struct RipgrepConfig {
     /// Default search mode: Original or Fluid
     pub default_mode: SearchMode,
@@ -60,6 +240,7 @@ pub(crate) struct RipgrepConfig {
     /// Fuzzy matching threshold (0.0-1.0)
     pub fuzzy_threshold: f32,
     /// Maximum edit distance for typo tolerance
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub max_edit_distance: Option<usize>,
     /// Disable heuristic scoring (use only fuzzy matching)
     pub heuristic_disabled: bool,
@@ -79,6 +260,38 @@ pub(crate) struct RipgrepConfig {
     pub timeout_ms: u64,
     /// Config version for future migrations
     pub version: u32,
+    /// Indexing-control policy for the incremental content cache
+    pub crawl: CrawlConfig,
+}
+
+/// Crawl/indexing policy for the incremental content cache. Bounds what gets
+/// crawled into the cache and how much memory building the index may use.
+/// Parsed from either a `[crawl]` table or flat dotted keys like
+/// `crawl.all_files = true` — TOML treats the two as equivalent.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct CrawlConfig {
+    /// Memory bound (MB) while building the crawl index. Crawling stops once
+    /// this is hit rather than running unbounded.
+    pub max_crawl_memory_mb: usize,
+    /// When false (default), respect `.gitignore` and hidden-file rules
+    /// while crawling. When true, index everything regardless.
+    pub all_files: bool,
+    /// Glob patterns to additionally include, even if otherwise ignored.
+    pub include: Vec<String>,
+    /// Glob patterns to exclude, even if not otherwise ignored.
+    pub exclude: Vec<String>,
+}
+
+impl Default for CrawlConfig {
+    fn default() -> Self {
+        CrawlConfig {
+            max_crawl_memory_mb: 256,
+            all_files: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
 }
 
 impl Default for RipgrepConfig {
@@ -96,7 +309,8 @@ impl Default for RipgrepConfig {
             cache_size_mb: 500,  // 500MB file content cache
             min_pattern_length: 1,  // Minimum pattern length
             timeout_ms: 0,  // No timeout by default
-            version: 1,
+            version: CONFIG_VERSION,
+            crawl: CrawlConfig::default(),
         }
     }
 }
@@ -116,14 +330,29 @@ impl RipgrepConfig {
             }
         }
 
+        // Validate the crawl memory bound is non-zero and within a sane cap
+        if self.crawl.max_crawl_memory_mb == 0 {
+            self.crawl.max_crawl_memory_mb = 1;
+        }
+        if self.crawl.max_crawl_memory_mb > 100_000 {
+            self.crawl.max_crawl_memory_mb = 100_000;
+        }
+
         Ok(())
     }
 }
 
 impl RipgrepConfig {
-    /// Get the path to the ripgrep config file.
-    /// Supports XDG Base Directory Specification on Unix-like systems.
+    /// Get the path to the global ripgrep config file.
+    ///
+    /// Follows ripgrep's own layered resolution: an explicit
+    /// `FLUIDGREP_CONFIG_PATH` (or `RIPGREP_CONFIG_PATH`) env var wins;
+    /// otherwise falls back to the XDG/APPDATA default path.
     pub(crate) fn config_path() -> Result<PathBuf> {
+        if let Some(path) = Self::env_config_path() {
+            return Ok(path);
+        }
+
         let config_dir = if cfg!(target_os = "windows") {
             // Windows: %APPDATA%\ripgrep
             match std::env::var("APPDATA") {
@@ -155,22 +384,101 @@ impl RipgrepConfig {
         Ok(config_dir.join("config.toml"))
     }
 
-    /// Load configuration from file.
+    /// Checks `FLUIDGREP_CONFIG_PATH`, then `RIPGREP_CONFIG_PATH`, for an
+    /// explicit override pointing directly at a config file.
+    fn env_config_path() -> Option<PathBuf> {
+        for var in ["FLUIDGREP_CONFIG_PATH", "RIPGREP_CONFIG_PATH"] {
+            if let Ok(path) = std::env::var(var) {
+                if !path.is_empty() {
+                    return Some(PathBuf::from(path));
+                }
+            }
+        }
+        None
+    }
+
+    /// Walks upward from `start`, returning the first project-local
+    /// `.fluidgreprc` found.
+    fn find_project_config(start: &Path) -> Option<PathBuf> {
+        let mut dir = Some(start);
+        while let Some(d) = dir {
+            let candidate = d.join(".fluidgreprc");
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+            dir = d.parent();
+        }
+        None
+    }
+
+    /// Load the effective configuration, discarding the record of which
+    /// files contributed. See `load_with_sources` for the full layering.
     pub(crate) fn load() -> Result<Self> {
-        let config_path = Self::config_path()?;
+        Self::load_with_sources().map(|(config, _sources)| config)
+    }
+
+    /// Loads the effective configuration by merging, in order:
+    /// 1. `RipgrepConfig::default()`
+    /// 2. the global config file (env override or XDG/APPDATA path), with
+    ///    older schema versions migrated and persisted back in place
+    /// 3. a project-local `.fluidgreprc`, found by walking up from the
+    ///    current directory, which wins field-by-field over the global config
+    ///
+    /// Returns the resolved config together with a `ConfigSources` record of
+    /// which files, if any, contributed, so callers can report the effective
+    /// source of each setting.
+    pub(crate) fn load_with_sources() -> Result<(Self, ConfigSources)> {
+        let mut sources = ConfigSources::default();
+        let mut table = toml::value::Table::new();
 
-        if !config_path.exists() {
-            return Ok(Self::default());
+        let global_path = Self::config_path()?;
+        if global_path.exists() {
+            let content = fs::read_to_string(&global_path)?;
+            let (global_table, migrated) = Self::migrate_table(&content)?;
+
+            if migrated {
+                let migrated_config: Self = toml::Value::Table(global_table.clone())
+                    .try_into()
+                    .map_err(|e| anyhow!("failed to parse config: {}", e))?;
+                migrated_config.save_to(&global_path)?;
+            }
+
+            table = global_table;
+            sources.global = Some(global_path);
+        }
+
+        if let Ok(cwd) = std::env::current_dir() {
+            if let Some(project_path) = Self::find_project_config(&cwd) {
+                let content = fs::read_to_string(&project_path)?;
+                let (project_table, _migrated) = Self::migrate_table(&content)?;
+
+                // Project overrides win field-by-field; fields it doesn't
+                // mention keep whatever the global layer (or defaults) set.
+                for (key, value) in project_table {
+                    table.insert(key, value);
+                }
+                sources.project = Some(project_path);
+            }
         }
 
-        let content = fs::read_to_string(&config_path)?;
-        Self::parse_toml(&content)
+        let mut config: Self = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| anyhow!("failed to parse merged config: {}", e))?;
+        config.validate()?;
+
+        Ok((config, sources))
     }
 
-    /// Save configuration to file using atomic writes.
-    /// Creates directory if needed and uses temp file to prevent corruption.
+    /// Save configuration to the resolved global config path using atomic
+    /// writes.
     pub(crate) fn save(&self) -> Result<()> {
-        let config_path = Self::config_path()?;
+        self.save_to(&Self::config_path()?)
+    }
+
+    /// Save configuration to an explicit path using atomic writes.
+    /// Creates the parent directory if needed and uses a temp file to
+    /// prevent corruption.
+    fn save_to(&self, config_path: &Path) -> Result<()> {
         let config_dir = config_path.parent().ok_or_else(|| anyhow!("invalid config path"))?;
 
         // Create directory with proper error handling
@@ -189,122 +497,289 @@ impl RipgrepConfig {
         drop(file);
 
         // Atomically rename temp to final
-        fs::rename(&temp_path, &config_path)
+        fs::rename(&temp_path, config_path)
             .map_err(|e| anyhow!("failed to save config: {}", e))?;
 
         Ok(())
     }
 
-    /// Parse TOML configuration (simple implementation).
-    /// Validates all values and applies defaults for invalid entries.
+    /// Parse TOML configuration via serde.
+    /// Missing fields fall back to `RipgrepConfig::default()` (via `#[serde(default)]`)
+    /// and unrecognized keys are ignored for forward compatibility.
     fn parse_toml(content: &str) -> Result<Self> {
-        let mut config = Self::default();
+        Self::parse_and_migrate(content).map(|(config, _migrated)| config)
+    }
 
-        for line in content.lines() {
-            let line = line.trim();
-            if line.is_empty() || line.starts_with('#') {
-                continue;
-            }
+    /// Parses TOML into a config, running the migration chain if the file's
+    /// `version` is older than `CONFIG_VERSION`. Returns the config plus
+    /// whether a migration actually ran, so `load()` knows whether to
+    /// rewrite the file.
+    fn parse_and_migrate(content: &str) -> Result<(Self, bool)> {
+        let (table, migrated) = Self::migrate_table(content)?;
 
-            // Remove inline comments
-            let line = if let Some(pos) = line.find('#') {
-                &line[..pos].trim()
-            } else {
-                line
-            };
-
-            if let Some((key, value)) = line.split_once('=') {
-                let key = key.trim();
-                let value = value.trim().trim_matches('"').trim_matches('\'').trim();
-
-                match key {
-                    "default_mode" => {
-                        config.default_mode = SearchMode::from_str(value).unwrap_or_default();
-                    }
-                    "fluid_disabled" => {
-                        config.fluid_disabled = value == "true" || value == "1";
-                    }
-                    "fuzzy_threshold" => {
-                        if let Ok(v) = value.parse::<f32>() {
-                            config.fuzzy_threshold = v;
-                        }
-                    }
-                    "max_edit_distance" => {
-                        if let Ok(v) = value.parse::<usize>() {
-                            config.max_edit_distance = Some(v);
-                        }
-                    }
-                    "heuristic_disabled" => {
-                        config.heuristic_disabled = value == "true" || value == "1";
-                    }
-                    "word_boundary_bonus" => {
-                        if let Ok(v) = value.parse::<f32>() {
-                            config.word_boundary_bonus = v;
-                        }
-                    }
-                    "consecutive_match_bonus" => {
-                        if let Ok(v) = value.parse::<f32>() {
-                            config.consecutive_match_bonus = v;
-                        }
-                    }
-                    "max_results" => {
-                        if let Ok(v) = value.parse::<usize>() {
-                            config.max_results = v;
-                        }
-                    }
-                    "enable_incremental" => {
-                        config.enable_incremental = value == "true" || value == "1";
-                    }
-                    "cache_size_mb" => {
-                        if let Ok(v) = value.parse::<usize>() {
-                            config.cache_size_mb = v;
-                        }
-                    }
-                    "min_pattern_length" => {
-                        if let Ok(v) = value.parse::<usize>() {
-                            config.min_pattern_length = v;
-                        }
-                    }
-                    "timeout_ms" => {
-                        if let Ok(v) = value.parse::<u64>() {
-                            config.timeout_ms = v;
-                        }
-                    }
-                    "version" => {
-                        if let Ok(v) = value.parse::<u32>() {
-                            config.version = v;
-                        }
-                    }
-                    _ => {} // Ignore unknown keys for forward compatibility
-                }
-            }
-        }
+        let mut config: Self = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| anyhow!("failed to parse config: {}", e))?;
 
-        // Validate all values
         config.validate()?;
 
-        Ok(config)
+        Ok((config, migrated))
     }
 
-    /// Convert to TOML format with comments and version.
+    /// Parses raw TOML into a table and runs the schema migration chain in
+    /// place, returning the up-to-date table plus whether a migration
+    /// actually ran. Shared by `parse_and_migrate` and `load_with_sources`
+    /// (which needs the table, not a fully-built config, to merge project
+    /// overrides on top field-by-field).
+    fn migrate_table(content: &str) -> Result<(toml::value::Table, bool)> {
+        let mut table: toml::value::Table = toml::from_str(content)
+            .map_err(|e| anyhow!("failed to parse config: {}", e))?;
+
+        let file_version = table
+            .get("version")
+            .and_then(|v| v.as_integer())
+            .unwrap_or(1) as u32;
+
+        let mut version = file_version;
+        if version < 2 {
+            migrate_v1_to_v2(&mut table);
+            version = 2;
+        }
+        // Future schema bumps add another `if version < N { migrate_vN1_to_vN(&mut table); version = N; }` here.
+        // A version newer than anything this binary knows how to migrate
+        // means the config was written by a newer build (or is simply
+        // corrupt) -- fail loudly rather than silently truncating it down
+        // to CONFIG_VERSION, which would risk misreading fields the
+        // migration chain never learned to handle.
+        if version > CONFIG_VERSION {
+            return Err(anyhow!(
+                "config file version {} is newer than this build supports ({})",
+                version,
+                CONFIG_VERSION
+            ));
+        }
+        table.insert("version".to_string(), toml::Value::Integer(version as i64));
+
+        Ok((table, version > file_version))
+    }
+
+    /// Convert to annotated TOML, generating one comment block per field from
+    /// `config_field_docs()` so every tunable documents its own hint, range,
+    /// and default instead of being silently omitted.
     fn to_toml(&self) -> String {
         let mut content = String::from("# Ripgrep Configuration\n");
-        content.push_str("# Version for future migrations\n");
-        content.push_str(&format!("version = {}\n", self.version));
-        content.push_str("\n# Default search mode: \"fluid\" or \"original\"\n");
-        content.push_str(&format!("default_mode = \"{}\"\n", self.default_mode.as_str()));
-        content.push_str("\n# Disable fluid mode permanently\n");
-        content.push_str(&format!("fluid_disabled = {}\n", self.fluid_disabled));
-        content.push_str("\n# Fuzzy matching threshold (0.0-1.0)\n");
-        content.push_str(&format!("fuzzy_threshold = {}\n", self.fuzzy_threshold));
+        content.push_str("# Generated from the field registry in `config_field_docs()`.\n\n");
 
-        if let Some(distance) = self.max_edit_distance {
-            content.push_str("\n# Maximum edit distance for typo tolerance\n");
-            content.push_str(&format!("max_edit_distance = {}\n", distance));
+        for doc in config_field_docs() {
+            content.push_str(&format!("# {} ({}", doc.description, doc.hint));
+            if let Some(range) = doc.range {
+                content.push_str(&format!(", range: {}", range));
+            }
+            content.push_str(&format!(", default: {})\n", doc.default));
+
+            if let Some(value) = self.field_toml_value(doc.key) {
+                content.push_str(&format!("{} = {}\n\n", doc.key, value));
+            }
         }
 
+        content.push_str("[crawl]\n");
+        content.push_str("# Memory bound (MB) while building the crawl index (range: 1-100000, default: 256)\n");
+        content.push_str(&format!("max_crawl_memory_mb = {}\n\n", self.crawl.max_crawl_memory_mb));
+        content.push_str("# When false, respect .gitignore/hidden-file rules; when true, index everything (default: false)\n");
+        content.push_str(&format!("all_files = {}\n\n", self.crawl.all_files));
+        content.push_str("# Glob patterns to additionally include, even if otherwise ignored\n");
+        content.push_str(&format!("include = {}\n\n", Self::format_toml_string_array(&self.crawl.include)));
+        content.push_str("# Glob patterns to exclude, even if not otherwise ignored\n");
+        content.push_str(&format!("exclude = {}\n\n", Self::format_toml_string_array(&self.crawl.exclude)));
+
         content
     }
+
+    /// Formats a list of strings as a TOML array literal, e.g. `["*.rs", "*.lock"]`.
+    fn format_toml_string_array(items: &[String]) -> String {
+        let quoted: Vec<String> = items.iter().map(|s| format!("{:?}", s)).collect();
+        format!("[{}]", quoted.join(", "))
+    }
+
+    /// Formats a single field's current value as a TOML literal, keyed by
+    /// field name. Floats are formatted directly from the `f32` value rather
+    /// than routed through `toml::Value` (which widens to `f64` and prints
+    /// precision noise like `0.699999988079071` for `0.7_f32`).
+    fn field_toml_value(&self, key: &str) -> Option<String> {
+        Some(match key {
+            "version" => self.version.to_string(),
+            "default_mode" => format!("\"{}\"", self.default_mode.as_str()),
+            "fluid_disabled" => self.fluid_disabled.to_string(),
+            "fuzzy_threshold" => self.fuzzy_threshold.to_string(),
+            "max_edit_distance" => self.max_edit_distance?.to_string(),
+            "heuristic_disabled" => self.heuristic_disabled.to_string(),
+            "word_boundary_bonus" => self.word_boundary_bonus.to_string(),
+            "consecutive_match_bonus" => self.consecutive_match_bonus.to_string(),
+            "max_results" => self.max_results.to_string(),
+            "enable_incremental" => self.enable_incremental.to_string(),
+            "cache_size_mb" => self.cache_size_mb.to_string(),
+            "min_pattern_length" => self.min_pattern_length.to_string(),
+            "timeout_ms" => self.timeout_ms.to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Prints the fully documented default config to stdout, for the
+    /// `--print-config`/`--config-docs` CLI flag.
+    pub(crate) fn print_config_docs() {
+        println!("{}", RipgrepConfig::default().to_toml());
+    }
+}
+
+/// Migrates a v1 config table to v2 in place.
+/// v1 releases spelled the fuzzy threshold key as `fuzz_threshold`; v2 renamed
+/// it to `fuzzy_threshold` for consistency with the struct field. Any other
+/// fields introduced in v2 are left unset here and fall back to
+/// `RipgrepConfig::default()` via `#[serde(default)]` once deserialized.
+fn migrate_v1_to_v2(table: &mut toml::value::Table) {
+    if let Some(value) = table.remove("fuzz_threshold") {
+        table.entry("fuzzy_threshold".to_string()).or_insert(value);
+    }
+}
+
+/// Strict, fail-fast alternative to `RipgrepConfig::validate()` for embedders
+/// constructing a config programmatically (in the spirit of sled's `Config`
+/// builder). Where the file-parsing path clamps out-of-range values for
+/// forward compatibility, each setter here range-checks its argument and
+/// returns a descriptive error instead, so callers find out immediately that
+/// their input was wrong.
+#[derive(Clone, Debug)]
+pub(crate) struct RipgrepConfigBuilder {
+    config: RipgrepConfig,
+}
+
+impl RipgrepConfigBuilder {
+    /// Starts from `RipgrepConfig::default()`.
+    pub(crate) fn new() -> Self {
+        RipgrepConfigBuilder {
+            config: RipgrepConfig::default(),
+        }
+    }
+
+    pub(crate) fn default_mode(mut self, mode: SearchMode) -> Self {
+        self.config.default_mode = mode;
+        self
+    }
+
+    pub(crate) fn fluid_disabled(mut self, disabled: bool) -> Self {
+        self.config.fluid_disabled = disabled;
+        self
+    }
+
+    /// Sets the fuzzy matching threshold. Must be within `0.0..=1.0`.
+    pub(crate) fn fuzzy_threshold(mut self, threshold: f32) -> Result<Self> {
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(anyhow!(
+                "fuzzy_threshold must be between 0.0 and 1.0, got {}",
+                threshold
+            ));
+        }
+        self.config.fuzzy_threshold = threshold;
+        Ok(self)
+    }
+
+    /// Sets the maximum edit distance. Must be at most 100.
+    pub(crate) fn max_edit_distance(mut self, distance: usize) -> Result<Self> {
+        if distance > 100 {
+            return Err(anyhow!(
+                "max_edit_distance must be at most 100, got {}",
+                distance
+            ));
+        }
+        self.config.max_edit_distance = Some(distance);
+        Ok(self)
+    }
+
+    pub(crate) fn heuristic_disabled(mut self, disabled: bool) -> Self {
+        self.config.heuristic_disabled = disabled;
+        self
+    }
+
+    /// Sets the word boundary bonus weight. Must be within `0.0..=1.0`.
+    pub(crate) fn word_boundary_bonus(mut self, bonus: f32) -> Result<Self> {
+        if !(0.0..=1.0).contains(&bonus) {
+            return Err(anyhow!(
+                "word_boundary_bonus must be between 0.0 and 1.0, got {}",
+                bonus
+            ));
+        }
+        self.config.word_boundary_bonus = bonus;
+        Ok(self)
+    }
+
+    pub(crate) fn consecutive_match_bonus(mut self, bonus: f32) -> Self {
+        self.config.consecutive_match_bonus = bonus;
+        self
+    }
+
+    pub(crate) fn max_results(mut self, max_results: usize) -> Self {
+        self.config.max_results = max_results;
+        self
+    }
+
+    pub(crate) fn enable_incremental(mut self, enable: bool) -> Self {
+        self.config.enable_incremental = enable;
+        self
+    }
+
+    pub(crate) fn cache_size_mb(mut self, size_mb: usize) -> Self {
+        self.config.cache_size_mb = size_mb;
+        self
+    }
+
+    pub(crate) fn min_pattern_length(mut self, len: usize) -> Self {
+        self.config.min_pattern_length = len;
+        self
+    }
+
+    pub(crate) fn timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.config.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// Sets the crawl memory bound (MB). Must be within `1..=100_000`.
+    pub(crate) fn crawl_max_memory_mb(mut self, mb: usize) -> Result<Self> {
+        if !(1..=100_000).contains(&mb) {
+            return Err(anyhow!(
+                "crawl.max_crawl_memory_mb must be between 1 and 100000, got {}",
+                mb
+            ));
+        }
+        self.config.crawl.max_crawl_memory_mb = mb;
+        Ok(self)
+    }
+
+    pub(crate) fn crawl_all_files(mut self, all_files: bool) -> Self {
+        self.config.crawl.all_files = all_files;
+        self
+    }
+
+    pub(crate) fn crawl_include(mut self, patterns: Vec<String>) -> Self {
+        self.config.crawl.include = patterns;
+        self
+    }
+
+    pub(crate) fn crawl_exclude(mut self, patterns: Vec<String>) -> Self {
+        self.config.crawl.exclude = patterns;
+        self
+    }
+
+    /// Assembles the validated config. Every setter already range-checked its
+    /// own input on the way in, so this never needs to clamp anything.
+    pub(crate) fn build(self) -> Result<RipgrepConfig> {
+        Ok(self.config)
+    }
+}
+
+impl Default for RipgrepConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Helper module for home directory detection.
@@ -326,6 +801,14 @@ mod dirs_home {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+
+    /// `std::env::set_var`/`set_current_dir` mutate genuinely global
+    /// process state, so tests that touch them must never run concurrently
+    /// with each other (the test harness otherwise runs tests in parallel
+    /// threads by default). Every such test takes this lock for its whole
+    /// body before touching the environment or cwd.
+    static ENV_MUTEX: Mutex<()> = Mutex::new(());
 
     #[test]
     fn test_default_config() {
@@ -342,7 +825,11 @@ mod tests {
         assert_eq!(config.cache_size_mb, 500);
         assert_eq!(config.min_pattern_length, 1);
         assert_eq!(config.timeout_ms, 0);
-        assert_eq!(config.version, 1);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.crawl.max_crawl_memory_mb, 256);
+        assert!(!config.crawl.all_files);
+        assert!(config.crawl.include.is_empty());
+        assert!(config.crawl.exclude.is_empty());
     }
 
     #[test]
@@ -389,6 +876,7 @@ fuzzy_threshold = 0.7
             min_pattern_length: 1,
             timeout_ms: 0,
             version: 1,
+            crawl: CrawlConfig::default(),
         };
         let toml = config.to_toml();
         assert!(toml.contains("default_mode = \"fluid\""));
@@ -413,6 +901,44 @@ fuzzy_threshold = 0.7
         assert_eq!(config.fuzzy_threshold, 0.6); // Reset to default
     }
 
+    #[test]
+    fn test_builder_rejects_out_of_range_threshold() {
+        // The file-parsing path clamps silently...
+        let content = "fuzzy_threshold = 1.5\n";
+        let clamped = RipgrepConfig::parse_toml(content).unwrap();
+        assert_eq!(clamped.fuzzy_threshold, 0.6);
+
+        // ...but the builder fails fast instead of guessing what was meant.
+        let err = RipgrepConfigBuilder::new().fuzzy_threshold(1.5);
+        assert!(err.is_err());
+
+        let err = RipgrepConfigBuilder::new().max_edit_distance(200);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_builder_builds_valid_config() {
+        let config = RipgrepConfigBuilder::new()
+            .default_mode(SearchMode::Original)
+            .fuzzy_threshold(0.9)
+            .unwrap()
+            .max_edit_distance(2)
+            .unwrap()
+            .max_results(25)
+            .crawl_max_memory_mb(128)
+            .unwrap()
+            .crawl_all_files(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(config.default_mode, SearchMode::Original);
+        assert_eq!(config.fuzzy_threshold, 0.9);
+        assert_eq!(config.max_edit_distance, Some(2));
+        assert_eq!(config.max_results, 25);
+        assert_eq!(config.crawl.max_crawl_memory_mb, 128);
+        assert!(config.crawl.all_files);
+    }
+
     #[test]
     fn test_max_edit_distance_validation() {
         let mut config = RipgrepConfig::default();
@@ -420,4 +946,190 @@ fuzzy_threshold = 0.7
         config.validate().unwrap();
         assert_eq!(config.max_edit_distance, Some(100)); // Capped
     }
+
+    #[test]
+    fn test_crawl_memory_bound_validation() {
+        let mut config = RipgrepConfig::default();
+        config.crawl.max_crawl_memory_mb = 0; // Invalid
+        config.validate().unwrap();
+        assert_eq!(config.crawl.max_crawl_memory_mb, 1);
+
+        config.crawl.max_crawl_memory_mb = 1_000_000; // Too large
+        config.validate().unwrap();
+        assert_eq!(config.crawl.max_crawl_memory_mb, 100_000); // Capped
+    }
+
+    #[test]
+    fn test_parse_crawl_section() {
+        let content = r#"
+version = 2
+[crawl]
+max_crawl_memory_mb = 64
+all_files = true
+include = ["*.rs"]
+exclude = ["*.lock"]
+"#;
+        let config = RipgrepConfig::parse_toml(content).unwrap();
+        assert_eq!(config.crawl.max_crawl_memory_mb, 64);
+        assert!(config.crawl.all_files);
+        assert_eq!(config.crawl.include, vec!["*.rs".to_string()]);
+        assert_eq!(config.crawl.exclude, vec!["*.lock".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_crawl_dotted_keys() {
+        let content = "version = 2\ncrawl.all_files = true\ncrawl.max_crawl_memory_mb = 42\n";
+        let config = RipgrepConfig::parse_toml(content).unwrap();
+        assert!(config.crawl.all_files);
+        assert_eq!(config.crawl.max_crawl_memory_mb, 42);
+    }
+
+    #[test]
+    fn test_to_toml_round_trip_all_fields() {
+        let config = RipgrepConfig {
+            default_mode: SearchMode::Original,
+            fluid_disabled: true,
+            fuzzy_threshold: 0.42,
+            max_edit_distance: Some(3),
+            heuristic_disabled: true,
+            word_boundary_bonus: 0.77,
+            consecutive_match_bonus: 1.25,
+            max_results: 123,
+            enable_incremental: false,
+            cache_size_mb: 42,
+            min_pattern_length: 2,
+            timeout_ms: 500,
+            version: 7,
+            crawl: CrawlConfig {
+                max_crawl_memory_mb: 64,
+                all_files: true,
+                include: vec!["*.rs".to_string()],
+                exclude: vec!["*.lock".to_string()],
+            },
+        };
+
+        let round_tripped = RipgrepConfig::parse_toml(&config.to_toml()).unwrap();
+        assert_eq!(round_tripped, config);
+    }
+
+    #[test]
+    fn test_migrate_v1_to_v2_renames_key_and_bumps_version() {
+        let content = r#"
+version = 1
+fuzz_threshold = 0.33
+"#;
+        let (config, migrated) = RipgrepConfig::parse_and_migrate(content).unwrap();
+        assert!(migrated);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.fuzzy_threshold, 0.33);
+    }
+
+    #[test]
+    fn test_parse_and_migrate_is_noop_for_current_version() {
+        let content = "version = 2\nfuzzy_threshold = 0.5\n";
+        let (config, migrated) = RipgrepConfig::parse_and_migrate(content).unwrap();
+        assert!(!migrated);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.fuzzy_threshold, 0.5);
+    }
+
+    #[test]
+    fn test_parse_and_migrate_rejects_future_version_instead_of_panicking() {
+        let content = format!("version = {}\n", CONFIG_VERSION + 1);
+        let err = RipgrepConfig::parse_and_migrate(&content).unwrap_err();
+        assert!(err.to_string().contains("newer than this build supports"));
+    }
+
+    #[test]
+    fn test_load_migrates_and_persists_old_config_file() {
+        let _guard = ENV_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = std::env::temp_dir()
+            .join(format!("fluid-grep-config-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        std::env::set_var("XDG_CONFIG_HOME", &dir);
+
+        let config_path = RipgrepConfig::config_path().unwrap();
+        fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        fs::write(&config_path, "version = 1\nfuzz_threshold = 0.5\n").unwrap();
+
+        let config = RipgrepConfig::load().unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.fuzzy_threshold, 0.5);
+
+        let persisted = fs::read_to_string(&config_path).unwrap();
+        assert!(persisted.contains(&format!("version = {}", CONFIG_VERSION)));
+
+        std::env::remove_var("XDG_CONFIG_HOME");
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_config_field_docs_cover_every_field() {
+        let docs = config_field_docs();
+        assert_eq!(docs.len(), 13); // one entry per `RipgrepConfig` field
+        assert!(docs.iter().any(|d| d.key == "word_boundary_bonus"));
+        assert!(docs.iter().any(|d| d.key == "consecutive_match_bonus"));
+
+        let search_mode_doc = docs.iter().find(|d| d.key == "default_mode").unwrap();
+        assert_eq!(search_mode_doc.hint, "fluid|original");
+    }
+
+    #[test]
+    fn test_to_toml_documents_previously_dropped_fields() {
+        let toml = RipgrepConfig::default().to_toml();
+        // Before this change these fields were silently dropped by to_toml.
+        assert!(toml.contains("# Word boundary bonus weight"));
+        assert!(toml.contains("word_boundary_bonus = 0.5"));
+        assert!(toml.contains("# Enable incremental caching"));
+        assert!(toml.contains("enable_incremental = true"));
+    }
+
+    #[test]
+    fn test_config_path_respects_env_override() {
+        let _guard = ENV_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        std::env::remove_var("RIPGREP_CONFIG_PATH");
+        std::env::set_var("FLUIDGREP_CONFIG_PATH", "/tmp/explicit-fluidgrep-config.toml");
+
+        let path = RipgrepConfig::config_path().unwrap();
+        assert_eq!(path, PathBuf::from("/tmp/explicit-fluidgrep-config.toml"));
+
+        std::env::remove_var("FLUIDGREP_CONFIG_PATH");
+    }
+
+    #[test]
+    fn test_load_with_sources_merges_project_override_field_by_field() {
+        let _guard = ENV_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        let base = std::env::temp_dir()
+            .join(format!("fluid-grep-layered-test-{}", std::process::id()));
+        let global_dir = base.join("global");
+        let project_dir = base.join("project");
+        fs::create_dir_all(&global_dir).unwrap();
+        fs::create_dir_all(&project_dir).unwrap();
+
+        let global_config_path = global_dir.join("fluidgrep.toml");
+        fs::write(
+            &global_config_path,
+            "version = 2\nfuzzy_threshold = 0.6\nmax_results = 50\n",
+        )
+        .unwrap();
+        fs::write(project_dir.join(".fluidgreprc"), "fuzzy_threshold = 0.95\n").unwrap();
+
+        std::env::set_var("FLUIDGREP_CONFIG_PATH", &global_config_path);
+        let original_cwd = std::env::current_dir().unwrap();
+        std::env::set_current_dir(&project_dir).unwrap();
+
+        let result = RipgrepConfig::load_with_sources();
+
+        std::env::set_current_dir(original_cwd).unwrap();
+        std::env::remove_var("FLUIDGREP_CONFIG_PATH");
+        fs::remove_dir_all(&base).ok();
+
+        let (config, sources) = result.unwrap();
+        // Project overrides fuzzy_threshold...
+        assert_eq!(config.fuzzy_threshold, 0.95);
+        // ...but max_results, absent from the project file, keeps the global value.
+        assert_eq!(config.max_results, 50);
+        assert_eq!(sources.global, Some(global_config_path));
+        assert!(sources.project.is_some());
+    }
 }
@@ -8,6 +8,8 @@ This module provides high-performance text searching using:
 Expected speedup: 2-4× for pattern matching operations.
 */
 
+use std::collections::{HashMap, VecDeque};
+
 use memchr;
 
 /// Fast pattern matching using memchr and optimized scalar operations
@@ -49,14 +51,55 @@ impl SimdMatcher {
         positions
     }
 
-    /// Scalar search for multi-byte patterns
+    /// Scalar search for multi-byte patterns, steered by the rarest byte in
+    /// the pattern. Probing on a rare byte instead of always `pattern[0]`
+    /// lets `memchr` skip far more of the text before we ever touch the full
+    /// comparison, which matters a lot when the first byte is something
+    /// common like a space. When every byte in the pattern is equally (un)common
+    /// per `BYTE_FREQUENCY`, this naturally falls back to probing on the
+    /// first occurrence, i.e. the old first-byte behavior.
     #[inline]
     fn find_scalar(pattern: &[u8], text: &[u8]) -> Vec<usize> {
+        let probe_idx = Self::rarest_byte_index(pattern);
+        Self::find_scalar_with_probe(pattern, text, probe_idx)
+    }
+
+    /// Index of the pattern byte with the lowest background frequency, i.e.
+    /// the best candidate to drive `memchr`. Ties resolve to the earliest
+    /// index, matching `Iterator::min_by_key`'s stable behavior.
+    fn rarest_byte_index(pattern: &[u8]) -> usize {
+        pattern
+            .iter()
+            .enumerate()
+            .min_by_key(|&(_, &byte)| BYTE_FREQUENCY[byte as usize])
+            .map(|(idx, _)| idx)
+            .unwrap_or(0)
+    }
+
+    /// Scan for `pattern` in `text` by jumping `memchr` to each occurrence of
+    /// `pattern[probe_idx]`, deriving the implied window start, and verifying
+    /// the full pattern there.
+    #[inline]
+    fn find_scalar_with_probe(pattern: &[u8], text: &[u8], probe_idx: usize) -> Vec<usize> {
         let mut positions = Vec::new();
+        let probe_byte = pattern[probe_idx];
+        let mut search_from = 0;
+
+        while let Some(found) = memchr::memchr(probe_byte, &text[search_from..]) {
+            let probe_pos = search_from + found;
+            search_from = probe_pos + 1;
 
-        for i in 0..=(text.len().saturating_sub(pattern.len())) {
-            if &text[i..i + pattern.len()] == pattern {
-                positions.push(i);
+            // The probe byte must land far enough into the text for a full
+            // window to fit before it.
+            if probe_pos < probe_idx {
+                continue;
+            }
+            let start = probe_pos - probe_idx;
+            if start + pattern.len() > text.len() {
+                continue;
+            }
+            if &text[start..start + pattern.len()] == pattern {
+                positions.push(start);
             }
         }
 
@@ -64,6 +107,51 @@ impl SimdMatcher {
     }
 }
 
+/// Approximate relative frequency of each byte across English prose and
+/// source code (higher = more common). Used by `SimdMatcher::find_scalar` to
+/// pick the rarest byte in a pattern as the `memchr` probe byte, since
+/// probing on a rare byte skips far more candidate windows than always
+/// probing on `pattern[0]`. Bytes with no explicit tuning default to a
+/// mid-range value so untuned bytes are still treated as "rarer" than the
+/// common ASCII letters/punctuation listed below.
+pub(crate) static BYTE_FREQUENCY: [u8; 256] = build_byte_frequency();
+
+const fn build_byte_frequency() -> [u8; 256] {
+    let mut table = [32u8; 256];
+    table[b' ' as usize] = 255;
+    table[b'e' as usize] = 250;
+    table[b't' as usize] = 245;
+    table[b'a' as usize] = 240;
+    table[b'o' as usize] = 235;
+    table[b'i' as usize] = 230;
+    table[b'n' as usize] = 225;
+    table[b's' as usize] = 220;
+    table[b'r' as usize] = 215;
+    table[b'h' as usize] = 210;
+    table[b'l' as usize] = 200;
+    table[b'd' as usize] = 195;
+    table[b'c' as usize] = 190;
+    table[b'u' as usize] = 185;
+    table[b'm' as usize] = 180;
+    table[b'\n' as usize] = 175;
+    table[b'(' as usize] = 170;
+    table[b')' as usize] = 170;
+    table[b'.' as usize] = 165;
+    table[b',' as usize] = 160;
+    table[b';' as usize] = 155;
+    table[b'_' as usize] = 150;
+    table[b'=' as usize] = 145;
+    table[b'{' as usize] = 140;
+    table[b'}' as usize] = 140;
+    table[b'f' as usize] = 135;
+    table[b'g' as usize] = 130;
+    table[b'p' as usize] = 125;
+    table[b'w' as usize] = 120;
+    table[b'y' as usize] = 115;
+    table[b'b' as usize] = 110;
+    table
+}
+
 impl Default for SimdMatcher {
     fn default() -> Self {
         Self::new()
@@ -93,6 +181,116 @@ impl Default for SimdCaseInsensitiveMatcher {
     }
 }
 
+/// Multi-pattern matcher using the Aho-Corasick automaton: finds all
+/// occurrences of many patterns in a single pass over the text, instead of
+/// running `SimdMatcher::find_all` once per pattern. Built for "search for
+/// any of these function names/literals" queries.
+pub struct SimdMultiMatcher {
+    /// `goto[node]` maps a byte to the child reached from `node`.
+    goto_links: Vec<HashMap<u8, usize>>,
+    /// `fail[node]` is the node to resume matching from after a mismatch.
+    fail_links: Vec<usize>,
+    /// `output[node]` holds the ids of patterns ending at `node`, including
+    /// those inherited via fail links during construction.
+    outputs: Vec<Vec<usize>>,
+    /// Byte length of each pattern, indexed by pattern id, used to turn a
+    /// match's end offset into a start offset.
+    pattern_lens: Vec<usize>,
+}
+
+const ROOT: usize = 0;
+
+impl SimdMultiMatcher {
+    /// Builds the Aho-Corasick automaton from a set of patterns.
+    pub fn new(patterns: &[&[u8]]) -> Self {
+        let mut goto_links: Vec<HashMap<u8, usize>> = vec![HashMap::new()];
+        let mut outputs: Vec<Vec<usize>> = vec![Vec::new()];
+        let pattern_lens: Vec<usize> = patterns.iter().map(|p| p.len()).collect();
+
+        // Build the trie.
+        for (pattern_id, pattern) in patterns.iter().enumerate() {
+            let mut node = ROOT;
+            for &byte in pattern.iter() {
+                node = *goto_links[node].entry(byte).or_insert_with(|| {
+                    goto_links.push(HashMap::new());
+                    outputs.push(Vec::new());
+                    goto_links.len() - 1
+                });
+            }
+            outputs[node].push(pattern_id);
+        }
+
+        // Compute fail links with a BFS from the root. The root's direct
+        // children fail to the root; every other node's fail link is
+        // goto(fail(parent), byte), following fail links until a match or
+        // the root, and its output set absorbs its fail target's output set.
+        let mut fail_links = vec![ROOT; goto_links.len()];
+        let mut queue = VecDeque::new();
+        for &child in goto_links[ROOT].values() {
+            fail_links[child] = ROOT;
+            queue.push_back(child);
+        }
+
+        while let Some(node) = queue.pop_front() {
+            let children: Vec<(u8, usize)> =
+                goto_links[node].iter().map(|(&b, &c)| (b, c)).collect();
+
+            for (byte, child) in children {
+                queue.push_back(child);
+
+                let mut fail_candidate = fail_links[node];
+                while fail_candidate != ROOT && !goto_links[fail_candidate].contains_key(&byte) {
+                    fail_candidate = fail_links[fail_candidate];
+                }
+
+                fail_links[child] = goto_links[fail_candidate]
+                    .get(&byte)
+                    .copied()
+                    .filter(|&target| target != child)
+                    .unwrap_or(ROOT);
+
+                let inherited = outputs[fail_links[child]].clone();
+                outputs[child].extend(inherited);
+            }
+        }
+
+        SimdMultiMatcher {
+            goto_links,
+            fail_links,
+            outputs,
+            pattern_lens,
+        }
+    }
+
+    /// Finds all occurrences of every pattern in `text` in one pass, grouped
+    /// per pattern id to mirror `SimdMatcher::find_all`'s `Vec<usize>` API.
+    /// `result[pattern_id]` holds the start offsets of that pattern's matches.
+    pub fn find_all(&self, text: &[u8]) -> Vec<Vec<usize>> {
+        let mut results: Vec<Vec<usize>> = vec![Vec::new(); self.pattern_lens.len()];
+        let mut node = ROOT;
+
+        for (i, &byte) in text.iter().enumerate() {
+            loop {
+                if let Some(&next) = self.goto_links[node].get(&byte) {
+                    node = next;
+                    break;
+                } else if node == ROOT {
+                    break;
+                } else {
+                    node = self.fail_links[node];
+                }
+            }
+
+            for &pattern_id in &self.outputs[node] {
+                let start = i + 1 - self.pattern_lens[pattern_id];
+                results[pattern_id].push(start);
+            }
+        }
+
+        results
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,4 +342,78 @@ mod tests {
         let positions = SimdMatcher::find_all(b"hello", b"");
         assert!(positions.is_empty());
     }
+
+    #[test]
+    fn test_multi_matcher_classic_aho_corasick_example() {
+        // Classic example: overlapping patterns sharing suffixes via fail links.
+        let matcher = SimdMultiMatcher::new(&[b"he", b"she", b"his", b"hers"]);
+        let matches = matcher.find_all(b"ushers");
+
+        assert_eq!(matches[0], vec![2]); // "he" in "ushers"
+        assert_eq!(matches[1], vec![1]); // "she" in "ushers"
+        assert_eq!(matches[2], Vec::<usize>::new()); // "his" does not occur
+        assert_eq!(matches[3], vec![2]); // "hers" in "ushers"
+    }
+
+    #[test]
+    fn test_multi_matcher_disjoint_patterns() {
+        let matcher = SimdMultiMatcher::new(&[b"fn", b"struct", b"impl"]);
+        let matches = matcher.find_all(b"fn main() { struct Foo; impl Foo {} }");
+
+        assert_eq!(matches[0], vec![0]);
+        assert_eq!(matches[1], vec![12]);
+        assert_eq!(matches[2], vec![24]);
+    }
+
+    #[test]
+    fn test_multi_matcher_overlapping_occurrences() {
+        let matcher = SimdMultiMatcher::new(&[b"aa"]);
+        let matches = matcher.find_all(b"aaaa");
+        assert_eq!(matches[0], vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_multi_matcher_no_patterns() {
+        let matcher = SimdMultiMatcher::new(&[]);
+        let matches = matcher.find_all(b"anything");
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_multi_matcher_empty_text() {
+        let matcher = SimdMultiMatcher::new(&[b"fn"]);
+        let matches = matcher.find_all(b"");
+        assert!(matches[0].is_empty());
+    }
+
+    #[test]
+    fn test_rarest_byte_index_prefers_uncommon_byte() {
+        // 'z' is far rarer than 'e' or 't' in BYTE_FREQUENCY.
+        assert_eq!(SimdMatcher::rarest_byte_index(b"tez"), 2);
+        assert_eq!(SimdMatcher::rarest_byte_index(b"zet"), 0);
+    }
+
+    #[test]
+    fn test_rarest_byte_index_ties_pick_first() {
+        // All bytes share the default frequency, so this should behave like
+        // the old first-byte probe.
+        assert_eq!(SimdMatcher::rarest_byte_index(b"xyz"), 0);
+    }
+
+    #[test]
+    fn test_find_scalar_with_rare_byte_probe() {
+        // Common leading byte ('e'), rare trailing byte ('z'): still finds
+        // every occurrence via the probe-and-verify path.
+        let text = b"everywhere the zebra grazed, everywhere";
+        let positions = SimdMatcher::find_all(b"everywhere", text);
+        assert_eq!(positions, vec![0, 29]);
+    }
+
+    #[test]
+    fn test_find_scalar_probe_near_text_boundaries() {
+        // Rare byte occurring too close to the start for a full window to
+        // fit must not produce a false match or panic on subtraction.
+        let positions = SimdMatcher::find_all(b"zany", b"z");
+        assert!(positions.is_empty());
+    }
 }